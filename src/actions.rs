@@ -1,23 +1,72 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-use crate::effect::Effect;
+use crate::{
+    effect::{Damage, DamageKind, Effect},
+    items::{Weapon, WeaponError, WeaponMode},
+    map::AttackRange,
+    reactions::{ReactionOutcome, ReadyTrigger},
+};
 
-#[derive(Clone, Debug, PartialEq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Attack {
     pub name: String,
     pub actor_id: u32,
     pub target_id: u32,
     pub effect: Effect,
+    pub range: AttackRange,
+    pub weapon: Option<WeaponAttack>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WeaponAttack {
+    pub weapon: Weapon,
+    pub mode: WeaponMode,
+    // Trade -5 to hit for +10 damage, e.g. with Great Weapon Master.
+    pub power_attack: bool,
+}
+
+impl Attack {
+    pub fn with_weapon(
+        actor_id: u32,
+        target_id: u32,
+        weapon: Weapon,
+        mode: WeaponMode,
+    ) -> Result<Self, WeaponError> {
+        weapon.check_mode(mode)?;
+
+        Ok(Self {
+            name: weapon.name.clone(),
+            actor_id,
+            target_id,
+            effect: Effect::Damage(Damage {
+                dice: weapon.damage_dice(mode),
+                kind: weapon.damage_kind.clone(),
+            }),
+            range: weapon.attack_range(mode),
+            weapon: Some(WeaponAttack {
+                weapon,
+                mode,
+                power_attack: false,
+            }),
+        })
+    }
+
+    pub fn with_power_attack(mut self) -> Self {
+        if let Some(weapon) = &mut self.weapon {
+            weapon.power_attack = true;
+        }
+        self
+    }
 }
 
 // TODO: Add support for targeting a point on the map. Maybe move target to the effect level?
-#[derive(Clone, Debug, PartialEq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Target {
     Actor(u32),
     Point(f32, f32),
 }
 
-#[derive(Clone, Debug, PartialEq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Shape {
     NoShape,
     Cone {
@@ -42,7 +91,7 @@ pub enum Shape {
     Point,
 }
 
-#[derive(Clone, Debug, PartialEq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct CastSpell {
     pub name: String,
     pub actor_id: u32,
@@ -51,7 +100,43 @@ pub struct CastSpell {
     pub shape: Shape,
 }
 
-#[derive(Clone, Debug, PartialEq, Serialize)]
+// Uses an item from the actor's inventory on the target, e.g. drinking a potion or reading a
+// spell scroll.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UseObject {
+    pub actor_id: u32,
+    pub target_id: u32,
+    pub item: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReadiedAction {
+    pub actor_id: u32,
+    pub trigger: ReadyTrigger,
+    pub action: Box<Action>,
+}
+
+// Limited-use class features that spend from the actor's resource pools.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum FeatureAction {
+    // Resistance to weapon damage and extra damage for a minute.
+    Rage,
+    // Font of Magic conversions between sorcery points and spell slots of the given level.
+    CreateSpellSlot(u32),
+    ConvertSpellSlot(u32),
+    // Applies the chosen option's effect, e.g. Preserve Life's healing.
+    ChannelDivinity { target_id: u32, effect: Effect },
+    // Gives an ally within 60 feet a Bardic Inspiration die.
+    BardicInspiration { target_id: u32 },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UseFeature {
+    pub actor_id: u32,
+    pub feature: FeatureAction,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Action {
     Attack(Attack),
     CastSpell(CastSpell),
@@ -60,15 +145,46 @@ pub enum Action {
     Dodge,
     Help,
     Hide,
-    Ready,
+    Ready(ReadiedAction),
     Search,
-    UseObject,
+    UseObject(UseObject),
+    UseFeature(UseFeature),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Outcome {
+    Hit { damage: i32, kind: DamageKind },
+    Miss,
+    OutOfRange,
+    TotalCover,
+    Countered,
+    Moved { feet: u32 },
+    Readied,
+    Applied,
+    Healed { amount: i32 },
+    // The item wasn't carried or has no charges left.
+    Unavailable,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ActionResult {
+    pub outcome: Outcome,
+    pub reactions: Vec<ReactionOutcome>,
+}
+
+impl ActionResult {
+    pub fn new(outcome: Outcome) -> Self {
+        Self {
+            outcome,
+            reactions: vec![],
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
-        effect::{Damage, DamageKind},
+        items::{WeaponCategory, WeaponKind, WeaponProperty},
         models::Dice,
     };
 
@@ -97,9 +213,39 @@ mod tests {
         let serialized_action = serde_json::to_string(&action).unwrap();
         println!("{}", serialized_action);
 
-        assert!(match action {
-            Action::CastSpell(_) => true,
-            _ => false,
-        });
+        assert!(matches!(action, Action::CastSpell(_)));
+    }
+
+    #[test]
+    fn test_construct_weapon_attack() {
+        let greataxe = Weapon::new(
+            "Greataxe",
+            WeaponCategory::Martial,
+            WeaponKind::Melee,
+            Dice {
+                count: 1,
+                sides: 12,
+            },
+            DamageKind::Slashing,
+        )
+        .with_properties(vec![WeaponProperty::Heavy, WeaponProperty::TwoHanded]);
+
+        assert_eq!(
+            Attack::with_weapon(1, 2, greataxe.clone(), WeaponMode::OneHanded),
+            Err(WeaponError::RequiresTwoHands(String::from("Greataxe")))
+        );
+
+        let attack = Attack::with_weapon(1, 2, greataxe, WeaponMode::TwoHanded).unwrap();
+        assert_eq!(attack.range, AttackRange::Melee { reach: 5 });
+        assert_eq!(
+            attack.effect,
+            Effect::Damage(Damage {
+                dice: Dice {
+                    count: 1,
+                    sides: 12
+                },
+                kind: DamageKind::Slashing,
+            })
+        );
     }
 }
@@ -0,0 +1,80 @@
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::{Item, ItemCategory, SkillType},
+    races::Trait,
+};
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct StartingItem {
+    pub name: String,
+    pub category: ItemCategory,
+    #[serde(default = "default_quantity")]
+    pub quantity: u32,
+    // Weight of a single unit in pounds.
+    #[serde(default)]
+    pub weight: f32,
+}
+
+fn default_quantity() -> u32 {
+    1
+}
+
+impl StartingItem {
+    pub fn item(&self) -> Item {
+        Item::new(&self.name, self.category.clone())
+            .with_quantity(self.quantity)
+            .with_weight(self.weight)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Background {
+    pub name: String,
+    pub skill_proficiencies: Vec<SkillType>,
+    #[serde(default)]
+    pub tool_proficiencies: Vec<String>,
+    // Number of languages of the player's choice.
+    #[serde(default)]
+    pub language_choices: usize,
+    pub equipment: Vec<StartingItem>,
+    // Gold pieces in the starting purse.
+    pub gold: u32,
+    pub feature: Trait,
+}
+
+pub fn backgrounds() -> &'static Vec<Background> {
+    static BACKGROUNDS: OnceLock<Vec<Background>> = OnceLock::new();
+    BACKGROUNDS
+        .get_or_init(|| serde_json::from_str(include_str!("../data/backgrounds.json")).unwrap())
+}
+
+pub fn background(name: &str) -> Option<&'static Background> {
+    backgrounds()
+        .iter()
+        .find(|background| background.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_background_data() {
+        assert_eq!(backgrounds().len(), 13);
+
+        let criminal = background("Criminal").unwrap();
+        assert_eq!(
+            criminal.skill_proficiencies,
+            vec![SkillType::Deception, SkillType::Stealth]
+        );
+        assert!(criminal
+            .tool_proficiencies
+            .contains(&String::from("Thieves' Tools")));
+        assert_eq!(criminal.equipment[0].item().weight, 5.0);
+        assert_eq!(background("Sage").unwrap().language_choices, 2);
+        assert!(background("Pirate").is_none());
+    }
+}
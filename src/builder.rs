@@ -0,0 +1,618 @@
+use std::collections::HashMap;
+
+use crate::{
+    backgrounds::background,
+    classes::{FeatureError, FightingStyle},
+    currency::{Denomination, Purse},
+    items::Equipment,
+    leveling::{Advancement, XP_THRESHOLDS},
+    models::{
+        Abilities, Ability, Character, Class, ClassDetails, Dice, HitPoints, Inventory,
+        Proficiency, Race, SavingThrow, SkillProficiencies, SkillType, Skills,
+    },
+    races::{race_definition, RaceDefinition, Subrace},
+};
+
+pub const POINT_BUY_BUDGET: u32 = 27;
+pub const STANDARD_ARRAY: [u32; 6] = [15, 14, 13, 12, 10, 8];
+pub const MAX_ABILITY_SCORE: u32 = 20;
+
+// Scores are given in the order strength, dexterity, constitution, intelligence, wisdom,
+// charisma.
+#[derive(Debug, PartialEq, Clone)]
+pub enum AbilityScores {
+    PointBuy([u32; 6]),
+    StandardArray([u32; 6]),
+    Rolled([u32; 6]),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum BuilderError {
+    MissingName,
+    MissingRace,
+    MissingSubrace,
+    UnknownSubrace(String),
+    // Racial ability choices must pick the right number of different abilities.
+    InvalidAbilityChoices,
+    MissingClass,
+    // Class skills must be the class's number of different skills from its list.
+    InvalidSkillChoices,
+    UnknownBackground(String),
+    // Chosen languages must be the background's number of languages not already known.
+    InvalidLanguageChoices,
+    // Characters at or above the subclass level need a subclass, and one fighting style per
+    // Fighting Style feature.
+    MissingSubclass,
+    MissingFightingStyle,
+    Feature(FeatureError),
+    MissingAbilityScores,
+    InvalidLevel(u32),
+    ScoreOutOfRange(u32),
+    OverBudget { spent: u32 },
+    NotStandardArray,
+    AbilityTooHigh(SavingThrow),
+}
+
+// Point cost of a score bought with point-buy, or `None` outside the 8-15 range.
+pub fn point_buy_cost(score: u32) -> Option<u32> {
+    match score {
+        8..=13 => Some(score - 8),
+        14 => Some(7),
+        15 => Some(9),
+        _ => None,
+    }
+}
+
+pub fn roll_4d6_drop_lowest() -> u32 {
+    let mut rolls = Dice { count: 4, sides: 6 }.roll().rolls;
+    rolls.sort();
+    rolls[1..].iter().sum::<i32>() as u32
+}
+
+pub fn roll_ability_scores() -> [u32; 6] {
+    [(); 6].map(|_| roll_4d6_drop_lowest())
+}
+
+impl AbilityScores {
+    pub fn validate(&self) -> Result<(), BuilderError> {
+        match self {
+            AbilityScores::PointBuy(scores) => {
+                let mut spent = 0;
+                for score in scores {
+                    spent += point_buy_cost(*score).ok_or(BuilderError::ScoreOutOfRange(*score))?;
+                }
+                if spent > POINT_BUY_BUDGET {
+                    return Err(BuilderError::OverBudget { spent });
+                }
+            }
+            AbilityScores::StandardArray(scores) => {
+                let mut sorted = *scores;
+                sorted.sort_by(|a, b| b.cmp(a));
+                if sorted != STANDARD_ARRAY {
+                    return Err(BuilderError::NotStandardArray);
+                }
+            }
+            AbilityScores::Rolled(scores) => {
+                if let Some(score) = scores.iter().find(|score| !(3..=18).contains(*score)) {
+                    return Err(BuilderError::ScoreOutOfRange(*score));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn scores(&self) -> [u32; 6] {
+        match self {
+            AbilityScores::PointBuy(scores)
+            | AbilityScores::StandardArray(scores)
+            | AbilityScores::Rolled(scores) => *scores,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct CharacterBuilder {
+    name: Option<String>,
+    race: Option<Race>,
+    subrace: Option<String>,
+    ability_choices: Vec<SavingThrow>,
+    class: Option<Class>,
+    level: u32,
+    ability_scores: Option<AbilityScores>,
+    class_skills: Vec<SkillType>,
+    skill_proficiencies: Vec<(SkillType, Proficiency)>,
+    background: Option<String>,
+    languages: Vec<String>,
+    subclass: Option<String>,
+    fighting_styles: Vec<FightingStyle>,
+    milestones: bool,
+}
+
+impl CharacterBuilder {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: Some(name.to_string()).filter(|name| !name.is_empty()),
+            level: 1,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_race(mut self, race: Race) -> Self {
+        self.race = Some(race);
+        self
+    }
+
+    pub fn with_subrace(mut self, subrace: &str) -> Self {
+        self.subrace = Some(subrace.to_string());
+        self
+    }
+
+    // Abilities that get the +1 of the race's free choices, e.g. a half-elf's two.
+    pub fn with_ability_choices(mut self, abilities: Vec<SavingThrow>) -> Self {
+        self.ability_choices = abilities;
+        self
+    }
+
+    pub fn with_class(mut self, class: Class) -> Self {
+        self.class = Some(class);
+        self
+    }
+
+    pub fn with_level(mut self, level: u32) -> Self {
+        self.level = level;
+        self
+    }
+
+    pub fn with_ability_scores(mut self, ability_scores: AbilityScores) -> Self {
+        self.ability_scores = Some(ability_scores);
+        self
+    }
+
+    pub fn with_class_skills(mut self, skills: Vec<SkillType>) -> Self {
+        self.class_skills = skills;
+        self
+    }
+
+    pub fn with_skill_proficiency(mut self, skill: SkillType, proficiency: Proficiency) -> Self {
+        self.skill_proficiencies.push((skill, proficiency));
+        self
+    }
+
+    pub fn with_background(mut self, background: &str) -> Self {
+        self.background = Some(background.to_string());
+        self
+    }
+
+    // Languages picked for the background's language choices.
+    pub fn with_languages(mut self, languages: Vec<String>) -> Self {
+        self.languages = languages;
+        self
+    }
+
+    pub fn with_subclass(mut self, subclass: &str) -> Self {
+        self.subclass = Some(subclass.to_string());
+        self
+    }
+
+    pub fn with_fighting_style(mut self, style: FightingStyle) -> Self {
+        self.fighting_styles.push(style);
+        self
+    }
+
+    // Level by story milestones rather than experience.
+    pub fn with_milestones(mut self) -> Self {
+        self.milestones = true;
+        self
+    }
+
+    fn race(&self) -> Result<(&'static RaceDefinition, Option<&'static Subrace>), BuilderError> {
+        let race = race_definition(self.race.as_ref().ok_or(BuilderError::MissingRace)?);
+        let subrace = match &self.subrace {
+            Some(name) => Some(
+                race.subrace(name)
+                    .ok_or_else(|| BuilderError::UnknownSubrace(name.clone()))?,
+            ),
+            None if !race.subraces.is_empty() => return Err(BuilderError::MissingSubrace),
+            None => None,
+        };
+
+        Ok((race, subrace))
+    }
+
+    // Chosen scores with racial bonuses applied.
+    pub fn abilities(&self) -> Result<Abilities, BuilderError> {
+        let (race, subrace) = self.race()?;
+        let ability_scores = self
+            .ability_scores
+            .as_ref()
+            .ok_or(BuilderError::MissingAbilityScores)?;
+        ability_scores.validate()?;
+
+        let [strength, dexterity, constitution, intelligence, wisdom, charisma] =
+            ability_scores.scores().map(|value| Ability { value });
+        let mut abilities = Abilities {
+            strength,
+            dexterity,
+            constitution,
+            intelligence,
+            wisdom,
+            charisma,
+        };
+        let choices_are_valid = self.ability_choices.len() == race.ability_choices
+            && self.ability_choices.iter().enumerate().all(|(i, ability)| {
+                !self.ability_choices[..i].contains(ability)
+                    && !race
+                        .ability_bonuses
+                        .iter()
+                        .any(|(bonus, _)| bonus == ability)
+            });
+        if !choices_are_valid {
+            return Err(BuilderError::InvalidAbilityChoices);
+        }
+
+        let bonuses = race
+            .ability_bonuses
+            .iter()
+            .chain(subrace.iter().flat_map(|subrace| &subrace.ability_bonuses))
+            .cloned()
+            .chain(
+                self.ability_choices
+                    .iter()
+                    .map(|ability| (ability.clone(), 1)),
+            );
+        for (ability, bonus) in bonuses {
+            abilities.get_mut(&ability).value += bonus;
+        }
+
+        match SavingThrow::ALL
+            .into_iter()
+            .find(|ability| abilities.get(ability).value > MAX_ABILITY_SCORE)
+        {
+            Some(ability) => Err(BuilderError::AbilityTooHigh(ability)),
+            None => Ok(abilities),
+        }
+    }
+
+    pub fn build(self, id: u32) -> Result<Character, BuilderError> {
+        let abilities = self.abilities()?;
+        let (race_definition, subrace) = self.race()?;
+        let name = self.name.ok_or(BuilderError::MissingName)?;
+        let race = self.race.ok_or(BuilderError::MissingRace)?;
+        let kind = self.class.ok_or(BuilderError::MissingClass)?;
+        if !(1..=20).contains(&self.level) {
+            return Err(BuilderError::InvalidLevel(self.level));
+        }
+
+        let mut class = ClassDetails::new(&kind);
+        class.level = self.level;
+        let class_definition = class.definition();
+        let skills_are_valid = self.class_skills.len() == class_definition.skill_choices
+            && self.class_skills.iter().enumerate().all(|(i, skill)| {
+                class_definition.skill_options.contains(skill)
+                    && !self.class_skills[..i].contains(skill)
+            });
+        if !skills_are_valid {
+            return Err(BuilderError::InvalidSkillChoices);
+        }
+
+        let background = match &self.background {
+            Some(name) => Some(
+                background(name).ok_or_else(|| BuilderError::UnknownBackground(name.clone()))?,
+            ),
+            None => None,
+        };
+        let mut languages = race_definition.languages.clone();
+        let language_choices = background.map_or(0, |background| background.language_choices);
+        let languages_are_valid = self.languages.len() == language_choices
+            && self.languages.iter().enumerate().all(|(i, language)| {
+                !languages.contains(language) && !self.languages[..i].contains(language)
+            });
+        if !languages_are_valid {
+            return Err(BuilderError::InvalidLanguageChoices);
+        }
+        languages.extend(self.languages);
+
+        let mut proficiencies = SkillProficiencies::default();
+        for skill in race_definition
+            .skill_proficiencies
+            .iter()
+            .chain(&self.class_skills)
+            .chain(
+                background
+                    .iter()
+                    .flat_map(|background| &background.skill_proficiencies),
+            )
+        {
+            proficiencies.set(skill, Proficiency::Proficient);
+        }
+        for (skill, proficiency) in self.skill_proficiencies {
+            proficiencies.set(&skill, proficiency);
+        }
+
+        let mut weapon_proficiencies = class_definition.weapon_proficiencies.clone();
+        weapon_proficiencies.extend(race_definition.weapon_proficiencies.iter().cloned());
+        let mut armor_proficiencies = class_definition.armor_proficiencies.clone();
+        let mut tool_proficiencies: Vec<String> = vec![];
+        for tool in class_definition.tool_proficiencies.iter().chain(
+            background
+                .iter()
+                .flat_map(|background| &background.tool_proficiencies),
+        ) {
+            if !tool_proficiencies.contains(tool) {
+                tool_proficiencies.push(tool.clone());
+            }
+        }
+        let mut inventory = Inventory {
+            items: vec![],
+            purse: Purse::default(),
+        };
+        if let Some(background) = background {
+            for item in &background.equipment {
+                inventory.add(item.item());
+            }
+            // An empty purse can always hold a background's starting gold.
+            inventory
+                .purse
+                .deposit(Denomination::Gold, background.gold, &background.name)
+                .unwrap();
+        }
+        let mut resistances = race_definition.resistances.clone();
+        let mut traits = race_definition.traits.clone();
+        let mut speed = race_definition.speed;
+        let mut darkvision = race_definition.darkvision;
+        let mut hit_points_per_level = 0;
+        if let Some(subrace) = subrace {
+            weapon_proficiencies.extend(subrace.weapon_proficiencies.iter().cloned());
+            armor_proficiencies.extend(subrace.armor_proficiencies.iter().cloned());
+            resistances.extend(subrace.resistances.iter().cloned());
+            traits.extend(subrace.traits.iter().cloned());
+            speed = subrace.speed.unwrap_or(speed);
+            darkvision = subrace.darkvision.unwrap_or(darkvision);
+            hit_points_per_level = subrace.hit_points_per_level;
+        }
+
+        // Maximum hit die at first level and the rounded-up average for every level after.
+        let constitution = abilities.constitution.get_modifier();
+        let hit_points = (class.hit_dice as i32
+            + constitution
+            + (self.level as i32 - 1) * (class.hit_dice as i32 / 2 + 1 + constitution))
+            .max(self.level as i32)
+            + hit_points_per_level * self.level as i32;
+
+        let mut character = Character {
+            id,
+            name,
+            race,
+            subrace: self.subrace,
+            background: self.background,
+            classes: vec![class],
+            advancement: if self.milestones {
+                Advancement::Milestone { level: self.level }
+            } else {
+                Advancement::Experience(XP_THRESHOLDS[self.level as usize - 1])
+            },
+            feats: vec![],
+            expended_spell_slots: [0; 9],
+            resources_spent: HashMap::new(),
+            inspiration: None,
+            skills: Skills::from_abilities(&abilities),
+            abilities,
+            proficiencies,
+            weapon_proficiencies,
+            armor_proficiencies,
+            tool_proficiencies,
+            saving_throw_proficiencies: class_definition.saving_throws.clone(),
+            inventory,
+            equipment: Equipment::default(),
+            hit_points: HitPoints {
+                current: hit_points,
+                max: hit_points,
+                temporary: 0,
+            },
+            armor_class: 10,
+            speed,
+            variant_encumbrance: false,
+            darkvision,
+            languages,
+            resistances,
+            traits,
+            active_effects: vec![],
+        };
+
+        match self.subclass {
+            Some(subclass) => character
+                .choose_subclass(&kind, &subclass)
+                .map_err(BuilderError::Feature)?,
+            None if self.level >= class_definition.subclass_level => {
+                return Err(BuilderError::MissingSubclass)
+            }
+            None => {}
+        }
+        for style in self.fighting_styles {
+            character
+                .choose_fighting_style(&kind, style)
+                .map_err(BuilderError::Feature)?;
+        }
+        let class = &character.classes[0];
+        if class.fighting_styles.len() < class.fighting_style_choices() {
+            return Err(BuilderError::MissingFightingStyle);
+        }
+        character.update_armor_class();
+
+        Ok(character)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{effect::DamageKind, items::ArmorCategory};
+
+    #[test]
+    fn test_ability_score_methods() {
+        assert_eq!(
+            AbilityScores::PointBuy([15, 15, 15, 8, 8, 8]).validate(),
+            Ok(())
+        );
+        assert_eq!(
+            AbilityScores::PointBuy([15, 15, 15, 9, 8, 8]).validate(),
+            Err(BuilderError::OverBudget { spent: 28 })
+        );
+        assert_eq!(
+            AbilityScores::PointBuy([16, 8, 8, 8, 8, 8]).validate(),
+            Err(BuilderError::ScoreOutOfRange(16))
+        );
+        assert_eq!(
+            AbilityScores::StandardArray([8, 10, 12, 13, 14, 15]).validate(),
+            Ok(())
+        );
+        assert_eq!(
+            AbilityScores::StandardArray([15, 15, 13, 12, 10, 8]).validate(),
+            Err(BuilderError::NotStandardArray)
+        );
+        assert_eq!(
+            AbilityScores::Rolled(roll_ability_scores()).validate(),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_build_character() {
+        let character = CharacterBuilder::new("Thokk")
+            .with_race(Race::HalfOrc)
+            .with_class(Class::Fighter)
+            .with_level(3)
+            .with_ability_scores(AbilityScores::StandardArray([15, 13, 14, 8, 12, 10]))
+            .with_class_skills(vec![SkillType::Athletics, SkillType::Perception])
+            .with_subclass("Champion")
+            .with_fighting_style(FightingStyle::Defense)
+            .build(1)
+            .unwrap();
+
+        assert_eq!(character.abilities.strength.value, 17);
+        assert_eq!(character.abilities.constitution.value, 15);
+        assert_eq!(character.abilities.intelligence.get_modifier(), -1);
+        assert_eq!(character.skills.athletics.value, 3);
+        assert_eq!(character.hit_points.max, 28);
+        assert_eq!(character.armor_class, 11);
+        assert!(character
+            .armor_proficiencies
+            .contains(&ArmorCategory::Heavy));
+        assert_eq!(
+            character.proficiencies.intimidation,
+            Proficiency::Proficient
+        );
+        assert_eq!(character.class_features().len(), 5);
+        assert!(character.has_fighting_style(FightingStyle::Defense));
+        assert_eq!(character.proficiencies.athletics, Proficiency::Proficient);
+
+        let missing_class = CharacterBuilder::new("Thokk")
+            .with_race(Race::HalfOrc)
+            .with_ability_scores(AbilityScores::StandardArray(STANDARD_ARRAY))
+            .build(1);
+        assert_eq!(missing_class, Err(BuilderError::MissingClass));
+
+        let fighter = CharacterBuilder::new("Thokk")
+            .with_race(Race::HalfOrc)
+            .with_class(Class::Fighter)
+            .with_ability_scores(AbilityScores::StandardArray(STANDARD_ARRAY))
+            .with_class_skills(vec![SkillType::Athletics, SkillType::Perception]);
+        assert_eq!(
+            fighter.clone().build(1),
+            Err(BuilderError::MissingFightingStyle)
+        );
+        assert_eq!(
+            fighter
+                .with_fighting_style(FightingStyle::Archery)
+                .with_subclass("Champion")
+                .build(1),
+            Err(BuilderError::Feature(FeatureError::SubclassTooEarly {
+                level: 3
+            }))
+        );
+    }
+
+    #[test]
+    fn test_build_character_with_subrace() {
+        let builder = CharacterBuilder::new("Bruenor")
+            .with_race(Race::Dwarf)
+            .with_class(Class::Fighter)
+            .with_class_skills(vec![SkillType::Athletics, SkillType::Survival])
+            .with_fighting_style(FightingStyle::Dueling)
+            .with_ability_scores(AbilityScores::PointBuy([15, 10, 15, 8, 12, 8]));
+        assert_eq!(builder.clone().build(1), Err(BuilderError::MissingSubrace));
+
+        let character = builder.with_subrace("Hill Dwarf").build(1).unwrap();
+        assert_eq!(character.abilities.constitution.value, 17);
+        assert_eq!(character.abilities.wisdom.value, 13);
+        assert_eq!(character.speed, 25);
+        assert_eq!(character.darkvision, 60);
+        assert_eq!(character.hit_points.max, 14);
+        assert!(character.resistances.contains(&DamageKind::Poison));
+        assert!(character.has_trait("Dwarven Toughness"));
+
+        let half_elf = CharacterBuilder::new("Tanis")
+            .with_race(Race::HalfElf)
+            .with_class(Class::Rogue)
+            .with_class_skills(vec![
+                SkillType::Acrobatics,
+                SkillType::Deception,
+                SkillType::Perception,
+                SkillType::Stealth,
+            ])
+            .with_ability_scores(AbilityScores::StandardArray(STANDARD_ARRAY));
+        assert_eq!(
+            half_elf
+                .clone()
+                .with_ability_choices(vec![SavingThrow::Charisma, SavingThrow::Wisdom])
+                .build(1),
+            Err(BuilderError::InvalidAbilityChoices)
+        );
+        let character = half_elf
+            .with_ability_choices(vec![SavingThrow::Strength, SavingThrow::Dexterity])
+            .build(1)
+            .unwrap();
+        assert_eq!(character.abilities.strength.value, 16);
+        assert_eq!(character.abilities.charisma.value, 10);
+    }
+
+    #[test]
+    fn test_build_character_with_background() {
+        let sage = CharacterBuilder::new("Elminster")
+            .with_race(Race::Human)
+            .with_class(Class::Wizard)
+            .with_class_skills(vec![SkillType::Insight, SkillType::Investigation])
+            .with_ability_scores(AbilityScores::StandardArray([8, 13, 14, 15, 12, 10]))
+            .with_background("Sage");
+        assert_eq!(
+            sage.clone().build(1),
+            Err(BuilderError::InvalidLanguageChoices)
+        );
+        assert_eq!(
+            sage.clone()
+                .with_languages(vec![String::from("Common"), String::from("Elvish")])
+                .build(1),
+            Err(BuilderError::InvalidLanguageChoices)
+        );
+        assert_eq!(
+            sage.clone().with_background("Pirate").build(1),
+            Err(BuilderError::UnknownBackground(String::from("Pirate")))
+        );
+
+        let character = sage
+            .with_languages(vec![String::from("Elvish"), String::from("Draconic")])
+            .build(1)
+            .unwrap();
+        assert_eq!(character.background, Some(String::from("Sage")));
+        assert_eq!(character.proficiencies.arcana, Proficiency::Proficient);
+        assert!(character.knows_language("Draconic"));
+        assert!(!character.knows_language("Dwarvish"));
+        assert_eq!(
+            character.saving_throw_proficiencies,
+            vec![SavingThrow::Intelligence, SavingThrow::Wisdom]
+        );
+        assert_eq!(character.inventory.quantity_of("Quill"), 1);
+        assert_eq!(character.inventory.purse.coins(Denomination::Gold), 10);
+    }
+}
@@ -0,0 +1,320 @@
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    items::{ArmorCategory, ArmorClassFormula, WeaponProficiency},
+    models::{Abilities, Class, Dice, SavingThrow, SkillType},
+    resources::Resource,
+};
+
+// Spell slots per spell level for a full caster of each level.
+const FULL_CASTER_SLOTS: [[u32; 9]; 20] = [
+    [2, 0, 0, 0, 0, 0, 0, 0, 0],
+    [3, 0, 0, 0, 0, 0, 0, 0, 0],
+    [4, 2, 0, 0, 0, 0, 0, 0, 0],
+    [4, 3, 0, 0, 0, 0, 0, 0, 0],
+    [4, 3, 2, 0, 0, 0, 0, 0, 0],
+    [4, 3, 3, 0, 0, 0, 0, 0, 0],
+    [4, 3, 3, 1, 0, 0, 0, 0, 0],
+    [4, 3, 3, 2, 0, 0, 0, 0, 0],
+    [4, 3, 3, 3, 1, 0, 0, 0, 0],
+    [4, 3, 3, 3, 2, 0, 0, 0, 0],
+    [4, 3, 3, 3, 2, 1, 0, 0, 0],
+    [4, 3, 3, 3, 2, 1, 0, 0, 0],
+    [4, 3, 3, 3, 2, 1, 1, 0, 0],
+    [4, 3, 3, 3, 2, 1, 1, 0, 0],
+    [4, 3, 3, 3, 2, 1, 1, 1, 0],
+    [4, 3, 3, 3, 2, 1, 1, 1, 0],
+    [4, 3, 3, 3, 2, 1, 1, 1, 1],
+    [4, 3, 3, 3, 3, 1, 1, 1, 1],
+    [4, 3, 3, 3, 3, 2, 1, 1, 1],
+    [4, 3, 3, 3, 3, 2, 2, 1, 1],
+];
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum CasterProgression {
+    Full,
+    Half,
+    Third,
+    // Warlock pact magic: a few slots, all of the same level, regained on a short rest.
+    Pact,
+}
+
+impl CasterProgression {
+    // Level on the full caster table for a single-classed caster.
+    pub fn caster_level(&self, class_level: u32) -> u32 {
+        match self {
+            CasterProgression::Full => class_level,
+            CasterProgression::Half if class_level >= 2 => class_level.div_ceil(2),
+            CasterProgression::Third if class_level >= 3 => class_level.div_ceil(3),
+            _ => 0,
+        }
+    }
+
+    // Contribution to the combined caster level of a multiclassed character, rounding down.
+    pub fn multiclass_caster_level(&self, class_level: u32) -> u32 {
+        match self {
+            CasterProgression::Full => class_level,
+            CasterProgression::Half => class_level / 2,
+            CasterProgression::Third => class_level / 3,
+            CasterProgression::Pact => 0,
+        }
+    }
+
+    pub fn spell_slots(&self, class_level: u32) -> [u32; 9] {
+        let mut slots = [0; 9];
+        if *self == CasterProgression::Pact {
+            let (count, slot_level) = match class_level {
+                0 => return slots,
+                1 => (1, 1),
+                2..=10 => (2, class_level.div_ceil(2).min(5)),
+                11..=16 => (3, 5),
+                _ => (4, 5),
+            };
+            slots[slot_level as usize - 1] = count;
+            return slots;
+        }
+
+        full_caster_slots(self.caster_level(class_level))
+    }
+}
+
+pub fn full_caster_slots(caster_level: u32) -> [u32; 9] {
+    match caster_level {
+        0 => [0; 9],
+        level => FULL_CASTER_SLOTS[level.min(20) as usize - 1],
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Spellcasting {
+    pub ability: SavingThrow,
+    pub progression: CasterProgression,
+    // Spells known at each class level, or the spells in a wizard's spellbook. Empty for
+    // classes that prepare spells from their whole list.
+    #[serde(default)]
+    pub spells_known: Vec<u32>,
+}
+
+impl Spellcasting {
+    pub fn spells_known(&self, class_level: u32) -> u32 {
+        match class_level {
+            0 => 0,
+            level => self
+                .spells_known
+                .get(level as usize - 1)
+                .copied()
+                .unwrap_or(0),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum FightingStyle {
+    // +2 to attack rolls with ranged weapons.
+    Archery,
+    // +1 AC while wearing armor.
+    Defense,
+    // +2 damage with a melee weapon in one hand and no other weapon.
+    Dueling,
+    // Reroll 1s and 2s on damage dice of two-handed melee attacks.
+    GreatWeaponFighting,
+    Protection,
+    // Add the ability modifier to the damage of off-hand attacks.
+    TwoWeaponFighting,
+}
+
+impl FightingStyle {
+    pub const ALL: [FightingStyle; 6] = [
+        FightingStyle::Archery,
+        FightingStyle::Defense,
+        FightingStyle::Dueling,
+        FightingStyle::GreatWeaponFighting,
+        FightingStyle::Protection,
+        FightingStyle::TwoWeaponFighting,
+    ];
+}
+
+// Mechanical effect a feature has once gained.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum FeatureEffect {
+    // Total number of attacks made with the Attack action.
+    ExtraAttack(u32),
+    SneakAttack(Dice),
+    // Grants the choice of one fighting style.
+    FightingStyle,
+    ArmorClassFormula(ArmorClassFormula),
+    Resource(Resource),
+    // Bonus to melee damage while raging.
+    RageDamage(i32),
+    // Sides of the Bardic Inspiration die.
+    InspirationDie(i32),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum MulticlassError {
+    // The character doesn't meet the ability prerequisites of the class they have or want.
+    PrerequisiteNotMet(Class),
+    MaxLevel,
+    InvalidSkillChoices,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum FeatureError {
+    NoClassLevels(Class),
+    UnknownSubclass(String),
+    SubclassTooEarly { level: u32 },
+    SubclassAlreadyChosen,
+    NoFightingStyleAvailable,
+    DuplicateFightingStyle(FightingStyle),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum SpellSlotError {
+    InvalidLevel(u32),
+    NoSlotsLeft(u32),
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ClassFeature {
+    pub level: u32,
+    pub name: String,
+    #[serde(default)]
+    pub effects: Vec<FeatureEffect>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Subclass {
+    pub name: String,
+    pub features: Vec<ClassFeature>,
+    // Subclasses such as the Eldritch Knight add spellcasting to a class without it.
+    #[serde(default)]
+    pub spellcasting: Option<Spellcasting>,
+}
+
+// Prerequisites for taking a level in the class as a second class, and the reduced set of
+// proficiencies it grants.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Multiclassing {
+    pub prerequisites: Vec<(SavingThrow, u32)>,
+    // Meeting one prerequisite is enough, e.g. Strength or Dexterity for a fighter.
+    #[serde(default)]
+    pub any_prerequisite: bool,
+    #[serde(default)]
+    pub armor_proficiencies: Vec<ArmorCategory>,
+    #[serde(default)]
+    pub weapon_proficiencies: Vec<WeaponProficiency>,
+    #[serde(default)]
+    pub tool_proficiencies: Vec<String>,
+    #[serde(default)]
+    pub skill_choices: usize,
+}
+
+impl Multiclassing {
+    pub fn meets_prerequisites(&self, abilities: &Abilities) -> bool {
+        let mut met = self
+            .prerequisites
+            .iter()
+            .map(|(ability, score)| abilities.get(ability).value >= *score);
+        if self.any_prerequisite {
+            met.any(|met| met)
+        } else {
+            met.all(|met| met)
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ClassDefinition {
+    pub class: Class,
+    pub hit_die: u32,
+    pub saving_throws: Vec<SavingThrow>,
+    pub armor_proficiencies: Vec<ArmorCategory>,
+    pub weapon_proficiencies: Vec<WeaponProficiency>,
+    pub tool_proficiencies: Vec<String>,
+    pub skill_choices: usize,
+    pub skill_options: Vec<SkillType>,
+    // Level at which the subclass is chosen.
+    pub subclass_level: u32,
+    pub features: Vec<ClassFeature>,
+    pub spellcasting: Option<Spellcasting>,
+    pub multiclassing: Multiclassing,
+    pub subclasses: Vec<Subclass>,
+}
+
+impl ClassDefinition {
+    pub fn subclass(&self, name: &str) -> Option<&Subclass> {
+        self.subclasses
+            .iter()
+            .find(|subclass| subclass.name == name)
+    }
+
+    pub fn features_at(&self, level: u32) -> impl Iterator<Item = &ClassFeature> {
+        self.features
+            .iter()
+            .filter(move |feature| feature.level == level)
+    }
+
+    pub fn spell_slots(&self, level: u32) -> [u32; 9] {
+        self.spellcasting.as_ref().map_or([0; 9], |spellcasting| {
+            spellcasting.progression.spell_slots(level)
+        })
+    }
+}
+
+pub fn classes() -> &'static Vec<ClassDefinition> {
+    static CLASSES: OnceLock<Vec<ClassDefinition>> = OnceLock::new();
+    CLASSES.get_or_init(|| serde_json::from_str(include_str!("../data/classes.json")).unwrap())
+}
+
+pub fn class_definition(class: &Class) -> &'static ClassDefinition {
+    classes()
+        .iter()
+        .find(|definition| definition.class == *class)
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_class_data() {
+        assert_eq!(classes().len(), 12);
+
+        let barbarian = class_definition(&Class::Barbarian);
+        assert_eq!(barbarian.hit_die, 12);
+        assert_eq!(
+            barbarian.saving_throws,
+            vec![SavingThrow::Strength, SavingThrow::Constitution]
+        );
+        assert_eq!(barbarian.features_at(5).count(), 2);
+
+        assert_eq!(
+            class_definition(&Class::Wizard).spell_slots(5),
+            [4, 3, 2, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            class_definition(&Class::Paladin).spell_slots(5),
+            [4, 2, 0, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(class_definition(&Class::Paladin).spell_slots(1), [0; 9]);
+        assert_eq!(
+            class_definition(&Class::Warlock).spell_slots(7),
+            [0, 0, 0, 2, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(class_definition(&Class::Fighter).spell_slots(20), [0; 9]);
+
+        let fighter = class_definition(&Class::Fighter);
+        let eldritch_knight = fighter.subclass("Eldritch Knight").unwrap();
+        assert_eq!(
+            eldritch_knight.spellcasting.as_ref().unwrap().progression,
+            CasterProgression::Third
+        );
+        assert_eq!(
+            fighter.features_at(11).next().unwrap().effects,
+            vec![FeatureEffect::ExtraAttack(3)]
+        );
+    }
+}
@@ -0,0 +1,534 @@
+use std::path::{Path, PathBuf};
+
+use clap::{Args, Parser, Subcommand};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::{
+    builder::{roll_ability_scores, AbilityScores, BuilderError, CharacterBuilder},
+    classes::FightingStyle,
+    models::{seed_rng, Character, Class, Dice, Race, SavingThrow, SkillType},
+    save::{load_session, save_session, SaveError},
+    state::{State, StateError, TerminalInterface},
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum CliError {
+    InvalidRoll(String),
+    // --scores needs one score per ability, or none to roll them.
+    InvalidScores(usize),
+    UnknownCharacter(u32),
+    Builder(BuilderError),
+    Save(SaveError),
+    State(StateError),
+}
+
+#[derive(Parser, Debug)]
+#[command(name = "vvt", version, about = "Volo's Virtual Toolkit")]
+pub struct Cli {
+    #[arg(long, global = true, help = "Print results as JSON")]
+    pub json: bool,
+    #[arg(long, global = true, help = "Seed the dice so rolls can be repeated")]
+    pub seed: Option<u64>,
+    #[arg(
+        long,
+        global = true,
+        default_value = "vvt-session.json",
+        help = "Session file the character and encounter commands work on"
+    )]
+    pub session: PathBuf,
+    // Without a command the interactive menu starts.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    #[command(about = "Roll dice, e.g. 2d6+3")]
+    Roll { expression: String },
+    #[command(about = "Create and manage the session's characters")]
+    Character {
+        #[command(subcommand)]
+        command: CharacterCommand,
+    },
+    #[command(about = "Run encounters with the session's characters")]
+    Encounter {
+        #[command(subcommand)]
+        command: EncounterCommand,
+    },
+    #[command(about = "Load and save whole sessions")]
+    Session {
+        #[command(subcommand)]
+        command: SessionCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CharacterCommand {
+    #[command(about = "Create a character")]
+    New(NewCharacter),
+    #[command(about = "Show a character's full sheet")]
+    Show { id: u32 },
+    #[command(about = "List all characters")]
+    List,
+    #[command(about = "Change a character's name, hit points or advancement")]
+    Edit(EditCharacter),
+    #[command(about = "Delete a character")]
+    Delete { id: u32 },
+}
+
+#[derive(Args, Debug)]
+pub struct NewCharacter {
+    pub name: String,
+    #[arg(long, value_parser = parse_name::<Race>)]
+    pub race: Race,
+    #[arg(long)]
+    pub subrace: Option<String>,
+    #[arg(long, value_parser = parse_name::<Class>)]
+    pub class: Class,
+    #[arg(long, default_value_t = 1)]
+    pub level: u32,
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "Scores from strength to charisma, rolled if left out"
+    )]
+    pub scores: Vec<u32>,
+    #[arg(
+        long,
+        help = "The scores were bought with points instead of the standard array"
+    )]
+    pub point_buy: bool,
+    #[arg(long, value_delimiter = ',', value_parser = parse_name::<SkillType>)]
+    pub skills: Vec<SkillType>,
+    #[arg(long, value_delimiter = ',', value_parser = parse_name::<SavingThrow>)]
+    pub ability_choices: Vec<SavingThrow>,
+    #[arg(long)]
+    pub background: Option<String>,
+    #[arg(long, value_delimiter = ',')]
+    pub languages: Vec<String>,
+    #[arg(long)]
+    pub subclass: Option<String>,
+    #[arg(long, value_delimiter = ',', value_parser = parse_name::<FightingStyle>)]
+    pub fighting_styles: Vec<FightingStyle>,
+    #[arg(long, help = "Level up at story milestones instead of with experience")]
+    pub milestones: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct EditCharacter {
+    pub id: u32,
+    #[arg(long)]
+    pub name: Option<String>,
+    #[arg(long, help = "Current hit points, up to the maximum")]
+    pub hit_points: Option<i32>,
+    #[arg(long, help = "Experience points to award")]
+    pub experience: Option<u32>,
+    #[arg(long, help = "Award a milestone")]
+    pub milestone: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum EncounterCommand {
+    #[command(about = "Roll initiative and print the turn order")]
+    Start {
+        #[arg(help = "Characters taking part, all of them if left out")]
+        ids: Vec<u32>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SessionCommand {
+    #[command(about = "Make a saved session the current one")]
+    Load { file: PathBuf },
+    #[command(about = "Save the current session to another file")]
+    Save { file: PathBuf },
+}
+
+// Parses enum variants by name, e.g. "HalfElf" or "AnimalHandling".
+fn parse_name<T: DeserializeOwned>(name: &str) -> Result<T, String> {
+    serde_json::from_value(Value::String(name.to_string()))
+        .map_err(|_| format!("unknown value {}", name))
+}
+
+// Limits on a roll expression, so every roll fits in memory and its total in an i32.
+const MAX_DICE: i32 = 1000;
+const MAX_SIDES: i32 = 1000;
+const MAX_MODIFIER: i32 = 1_000_000;
+
+// Dice expression such as 2d6+3 or 1d8+1d6-1. Dice are always added.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RollExpression {
+    pub dice: Vec<Dice>,
+    pub modifier: i32,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct RollOutput {
+    pub expression: String,
+    pub rolls: Vec<i32>,
+    pub modifier: i32,
+    pub total: i32,
+}
+
+impl RollExpression {
+    pub fn parse(expression: &str) -> Result<Self, CliError> {
+        let invalid = || CliError::InvalidRoll(expression.to_string());
+        let normalized = expression.replace(' ', "").replace('-', "+-");
+        let mut roll = RollExpression {
+            dice: vec![],
+            modifier: 0,
+        };
+        for (index, term) in normalized.split('+').enumerate() {
+            // Only a leading sign leaves an empty term.
+            if term.is_empty() && index == 0 {
+                continue;
+            }
+            match term.split_once(['d', 'D']) {
+                Some((count, sides)) => {
+                    let count = if count.is_empty() {
+                        1
+                    } else {
+                        count.parse::<i32>().map_err(|_| invalid())?
+                    };
+                    let sides = sides.parse::<i32>().map_err(|_| invalid())?;
+                    let total_count = roll.dice.iter().map(|dice| dice.count).sum::<i32>() + count;
+                    if count < 1 || sides < 1 || total_count > MAX_DICE || sides > MAX_SIDES {
+                        return Err(invalid());
+                    }
+                    roll.dice.push(Dice { count, sides });
+                }
+                None => {
+                    let modifier = term.parse::<i32>().map_err(|_| invalid())?;
+                    roll.modifier = roll
+                        .modifier
+                        .checked_add(modifier)
+                        .filter(|modifier| modifier.abs() <= MAX_MODIFIER)
+                        .ok_or_else(invalid)?;
+                }
+            }
+        }
+
+        if roll.dice.is_empty() {
+            return Err(invalid());
+        }
+        Ok(roll)
+    }
+
+    pub fn roll(&self, expression: &str) -> RollOutput {
+        let rolls: Vec<i32> = self
+            .dice
+            .iter()
+            .flat_map(|dice| dice.roll().rolls)
+            .collect();
+        RollOutput {
+            expression: expression.to_string(),
+            total: rolls.iter().sum::<i32>() + self.modifier,
+            rolls,
+            modifier: self.modifier,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct InitiativeEntry {
+    pub id: u32,
+    pub name: String,
+    pub initiative: i32,
+}
+
+// Starts with an empty session when the file doesn't exist yet.
+fn open_session(path: &Path) -> Result<State, CliError> {
+    if path.exists() {
+        load_session(path).map_err(CliError::Save)
+    } else {
+        Ok(State::new())
+    }
+}
+
+fn summary(character: &Character) -> String {
+    let classes: Vec<String> = character
+        .classes
+        .iter()
+        .map(|class| format!("{} {}", class.name, class.level))
+        .collect();
+    format!(
+        "{}: {} ({:?} {}), {}/{} HP",
+        character.id,
+        character.name,
+        character.race,
+        classes.join("/"),
+        character.hit_points.current,
+        character.hit_points.max
+    )
+}
+
+impl NewCharacter {
+    fn builder(self) -> Result<CharacterBuilder, CliError> {
+        let count = self.scores.len();
+        let ability_scores = match (self.scores.try_into(), self.point_buy) {
+            _ if count == 0 => AbilityScores::Rolled(roll_ability_scores()),
+            (Ok(scores), true) => AbilityScores::PointBuy(scores),
+            (Ok(scores), false) => AbilityScores::StandardArray(scores),
+            (Err(_), _) => return Err(CliError::InvalidScores(count)),
+        };
+        let mut builder = CharacterBuilder::new(&self.name)
+            .with_race(self.race)
+            .with_class(self.class)
+            .with_level(self.level)
+            .with_ability_scores(ability_scores)
+            .with_class_skills(self.skills)
+            .with_ability_choices(self.ability_choices)
+            .with_languages(self.languages);
+        if let Some(subrace) = &self.subrace {
+            builder = builder.with_subrace(subrace);
+        }
+        if let Some(background) = &self.background {
+            builder = builder.with_background(background);
+        }
+        if let Some(subclass) = &self.subclass {
+            builder = builder.with_subclass(subclass);
+        }
+        for style in self.fighting_styles {
+            builder = builder.with_fighting_style(style);
+        }
+        if self.milestones {
+            builder = builder.with_milestones();
+        }
+        Ok(builder)
+    }
+}
+
+impl Cli {
+    // Seeds the dice and runs the command, or the interactive menu when there is none.
+    pub fn run(self) -> Result<(), CliError> {
+        if let Some(seed) = self.seed {
+            seed_rng(seed);
+        }
+        match self.command {
+            Some(command) => {
+                println!("{}", execute(command, &self.session, self.json)?);
+            }
+            None => {
+                println!("Volo's Virtual Toolkit");
+                TerminalInterface::new().run();
+            }
+        }
+        Ok(())
+    }
+}
+
+fn render<T: Serialize + ?Sized>(json: bool, value: &T, text: String) -> String {
+    if json {
+        serde_json::to_string_pretty(value).unwrap()
+    } else {
+        text
+    }
+}
+
+// Runs a command against the session file and returns what it prints.
+pub fn execute(command: Command, session: &Path, json: bool) -> Result<String, CliError> {
+    match command {
+        Command::Roll { expression } => {
+            let result = RollExpression::parse(&expression)?.roll(&expression);
+            let text = format!("{}: {:?} = {}", expression, result.rolls, result.total);
+            Ok(render(json, &result, text))
+        }
+        Command::Character { command } => {
+            let mut state = open_session(session)?;
+            let result = match command {
+                CharacterCommand::New(options) => {
+                    let id = state.allocate_id();
+                    let character = options.builder()?.build(id).map_err(CliError::Builder)?;
+                    let text = format!("Created {}", summary(&character));
+                    let result = render(json, &character, text);
+                    state.add_character(character).map_err(CliError::State)?;
+                    result
+                }
+                CharacterCommand::Show { id } => {
+                    let character = state
+                        .get_character(id)
+                        .ok_or(CliError::UnknownCharacter(id))?;
+                    return Ok(render(json, character, format!("{:#?}", character)));
+                }
+                CharacterCommand::List => {
+                    let characters: Vec<&Character> = state.characters().collect();
+                    let lines: Vec<String> = characters.iter().map(|c| summary(c)).collect();
+                    return Ok(render(json, &characters, lines.join("\n")));
+                }
+                CharacterCommand::Edit(edit) => {
+                    let character = state
+                        .get_character_mut(edit.id)
+                        .ok_or(CliError::UnknownCharacter(edit.id))?;
+                    if let Some(name) = edit.name {
+                        character.name = name;
+                    }
+                    if let Some(hit_points) = edit.hit_points {
+                        character.hit_points.current =
+                            hit_points.clamp(0, character.hit_points.max);
+                    }
+                    if let Some(experience) = edit.experience {
+                        character.gain_experience(experience);
+                    }
+                    if edit.milestone {
+                        character.reach_milestone();
+                    }
+                    render(json, &*character, format!("Updated {}", summary(character)))
+                }
+                CharacterCommand::Delete { id } => {
+                    let character = state
+                        .remove_character(id)
+                        .ok_or(CliError::UnknownCharacter(id))?;
+                    render(json, &character, format!("Deleted {}", summary(&character)))
+                }
+            };
+            save_session(&state, session).map_err(CliError::Save)?;
+            Ok(result)
+        }
+        Command::Encounter {
+            command: EncounterCommand::Start { ids },
+        } => {
+            let state = open_session(session)?;
+            let characters = if ids.is_empty() {
+                state.characters().collect()
+            } else {
+                ids.iter()
+                    .map(|id| {
+                        state
+                            .get_character(*id)
+                            .ok_or(CliError::UnknownCharacter(*id))
+                    })
+                    .collect::<Result<Vec<&Character>, CliError>>()?
+            };
+            // Ties go to the higher Dexterity.
+            let mut order: Vec<(InitiativeEntry, u32)> = characters
+                .into_iter()
+                .map(|character| {
+                    let entry = InitiativeEntry {
+                        id: character.id,
+                        name: character.name.clone(),
+                        initiative: character.roll_initiative(),
+                    };
+                    (entry, character.abilities.dexterity.value)
+                })
+                .collect();
+            order.sort_by(|(a, a_dex), (b, b_dex)| {
+                (b.initiative, b_dex).cmp(&(a.initiative, a_dex))
+            });
+            let order: Vec<InitiativeEntry> = order.into_iter().map(|(entry, _)| entry).collect();
+            let lines: Vec<String> = order
+                .iter()
+                .map(|entry| format!("{} {} ({})", entry.initiative, entry.name, entry.id))
+                .collect();
+            Ok(render(json, &order, lines.join("\n")))
+        }
+        Command::Session { command } => match command {
+            SessionCommand::Load { file } => {
+                let state = load_session(&file).map_err(CliError::Save)?;
+                save_session(&state, session).map_err(CliError::Save)?;
+                let characters: Vec<&Character> = state.characters().collect();
+                let text = format!(
+                    "Loaded {} characters from {}",
+                    characters.len(),
+                    file.display()
+                );
+                Ok(render(json, &characters, text))
+            }
+            SessionCommand::Save { file } => {
+                let state = open_session(session)?;
+                save_session(&state, &file).map_err(CliError::Save)?;
+                Ok(render(
+                    json,
+                    &file,
+                    format!("Saved the session to {}", file.display()),
+                ))
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    fn run(args: &[&str], session: &Path) -> Result<String, CliError> {
+        let cli = Cli::try_parse_from([&["vvt"], args].concat()).unwrap();
+        execute(cli.command.unwrap(), session, cli.json)
+    }
+
+    #[test]
+    fn test_roll_expression() {
+        assert_eq!(
+            RollExpression::parse("2d6 + d4 - 1"),
+            Ok(RollExpression {
+                dice: vec![Dice { count: 2, sides: 6 }, Dice { count: 1, sides: 4 }],
+                modifier: -1,
+            })
+        );
+        for invalid in [
+            "",
+            "3",
+            "2d",
+            "d0",
+            "-1d6",
+            "2d6++3",
+            "2000000000d6",
+            "100000000d100",
+            "1d1001",
+            "600d6+600d6",
+            "1d6+2147483647+1",
+        ] {
+            assert_eq!(
+                RollExpression::parse(invalid),
+                Err(CliError::InvalidRoll(invalid.to_string()))
+            );
+        }
+
+        seed_rng(42);
+        let first = RollExpression::parse("4d6").unwrap().roll("4d6");
+        seed_rng(42);
+        assert_eq!(RollExpression::parse("4d6").unwrap().roll("4d6"), first);
+    }
+
+    #[test]
+    fn test_character_commands() {
+        let session = env::temp_dir().join("vvt_test_cli_session.json");
+        let _ = fs::remove_file(&session);
+        let new = [
+            "character",
+            "new",
+            "Thokk",
+            "--race",
+            "HalfOrc",
+            "--class",
+            "Fighter",
+            "--scores",
+            "15,14,13,12,10,8",
+            "--skills",
+            "Athletics,Intimidation",
+            "--fighting-styles",
+            "Defense",
+        ];
+        assert!(run(&new, &session).unwrap().starts_with("Created 1: Thokk"));
+        assert!(run(&new, &session).unwrap().starts_with("Created 2: Thokk"));
+        let mut short = new;
+        short[8] = "15,14,13";
+        assert_eq!(run(&short, &session), Err(CliError::InvalidScores(3)));
+
+        run(&["character", "edit", "2", "--name", "Grog"], &session).unwrap();
+        let list = run(&["character", "list", "--json"], &session).unwrap();
+        let characters: Vec<Character> = serde_json::from_str(&list).unwrap();
+        assert_eq!(characters[1].name, "Grog");
+
+        run(&["character", "delete", "1"], &session).unwrap();
+        assert_eq!(
+            run(&["character", "show", "1"], &session),
+            Err(CliError::UnknownCharacter(1))
+        );
+        let order = run(&["encounter", "start"], &session).unwrap();
+        assert!(order.ends_with("Grog (2)"));
+        fs::remove_file(&session).unwrap();
+    }
+}
@@ -0,0 +1,327 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Denomination {
+    Copper,
+    Silver,
+    Electrum,
+    Gold,
+    Platinum,
+}
+
+impl Denomination {
+    pub const ALL: [Denomination; 5] = [
+        Denomination::Copper,
+        Denomination::Silver,
+        Denomination::Electrum,
+        Denomination::Gold,
+        Denomination::Platinum,
+    ];
+
+    pub fn value_in_copper(&self) -> u32 {
+        match self {
+            Denomination::Copper => 1,
+            Denomination::Silver => 10,
+            Denomination::Electrum => 50,
+            Denomination::Gold => 100,
+            Denomination::Platinum => 1000,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CurrencyError {
+    InsufficientFunds { required: u32, available: u32 },
+    // The coins don't convert into a whole number of the other denomination.
+    UnevenExchange,
+    // The purse can't hold more than u32::MAX copper pieces worth of coins.
+    Overflow,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Transaction {
+    // Change in copper pieces, negative for money spent.
+    pub amount: i64,
+    pub balance: u32,
+    pub description: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Purse {
+    pub copper: u32,
+    pub silver: u32,
+    pub electrum: u32,
+    pub gold: u32,
+    pub platinum: u32,
+    pub transactions: Vec<Transaction>,
+}
+
+impl Purse {
+    pub fn coins(&self, denomination: Denomination) -> u32 {
+        match denomination {
+            Denomination::Copper => self.copper,
+            Denomination::Silver => self.silver,
+            Denomination::Electrum => self.electrum,
+            Denomination::Gold => self.gold,
+            Denomination::Platinum => self.platinum,
+        }
+    }
+
+    fn coins_mut(&mut self, denomination: Denomination) -> &mut u32 {
+        match denomination {
+            Denomination::Copper => &mut self.copper,
+            Denomination::Silver => &mut self.silver,
+            Denomination::Electrum => &mut self.electrum,
+            Denomination::Gold => &mut self.gold,
+            Denomination::Platinum => &mut self.platinum,
+        }
+    }
+
+    pub fn total_in_copper(&self) -> u32 {
+        Denomination::ALL
+            .iter()
+            .map(|denomination| self.coins(*denomination) * denomination.value_in_copper())
+            .sum()
+    }
+
+    // Coins weigh a pound per 50.
+    pub fn weight(&self) -> f32 {
+        Denomination::ALL
+            .iter()
+            .map(|denomination| self.coins(*denomination))
+            .sum::<u32>() as f32
+            / 50.0
+    }
+
+    fn record(&mut self, amount: i64, description: &str) {
+        self.transactions.push(Transaction {
+            amount,
+            balance: self.total_in_copper(),
+            description: description.to_string(),
+        });
+    }
+
+    // Checking the new total is enough, as no denomination's coins can be worth more than it.
+    pub fn deposit(
+        &mut self,
+        denomination: Denomination,
+        amount: u32,
+        description: &str,
+    ) -> Result<(), CurrencyError> {
+        let copper = amount
+            .checked_mul(denomination.value_in_copper())
+            .filter(|copper| self.total_in_copper().checked_add(*copper).is_some())
+            .ok_or(CurrencyError::Overflow)?;
+        *self.coins_mut(denomination) += amount;
+        self.record(copper as i64, description);
+        Ok(())
+    }
+
+    // Adds copper pieces worth of coins using as few coins as possible. Electrum is left out
+    // as most merchants don't hand it out.
+    fn add_change(&mut self, mut copper: u32) {
+        for denomination in [
+            Denomination::Platinum,
+            Denomination::Gold,
+            Denomination::Silver,
+            Denomination::Copper,
+        ] {
+            let value = denomination.value_in_copper();
+            *self.coins_mut(denomination) += copper / value;
+            copper %= value;
+        }
+    }
+
+    // Pays a cost in copper pieces, spending the smallest coins first and breaking a larger
+    // coin for change when the small ones don't add up.
+    pub fn pay(&mut self, cost: u32, description: &str) -> Result<(), CurrencyError> {
+        let available = self.total_in_copper();
+        if cost > available {
+            return Err(CurrencyError::InsufficientFunds {
+                required: cost,
+                available,
+            });
+        }
+
+        let mut owed = cost;
+        for denomination in Denomination::ALL {
+            let value = denomination.value_in_copper();
+            let spent = self.coins(denomination).min(owed / value);
+            *self.coins_mut(denomination) -= spent;
+            owed -= spent * value;
+        }
+
+        while owed > 0 {
+            let coin = Denomination::ALL
+                .into_iter()
+                .find(|denomination| {
+                    self.coins(*denomination) > 0 && denomination.value_in_copper() >= owed
+                })
+                .or_else(|| {
+                    Denomination::ALL
+                        .into_iter()
+                        .find(|denomination| self.coins(*denomination) > 0)
+                })
+                .unwrap();
+            let value = coin.value_in_copper();
+            *self.coins_mut(coin) -= 1;
+            if value > owed {
+                self.add_change(value - owed);
+                owed = 0;
+            } else {
+                owed -= value;
+            }
+        }
+
+        self.record(-(cost as i64), description);
+        Ok(())
+    }
+
+    // Exchanges coins of one denomination for another, e.g. 100 cp for 1 gp. The total is
+    // unchanged, so the transaction is recorded with an amount of 0.
+    pub fn exchange(
+        &mut self,
+        from: Denomination,
+        to: Denomination,
+        amount: u32,
+        description: &str,
+    ) -> Result<u32, CurrencyError> {
+        let copper = amount
+            .checked_mul(from.value_in_copper())
+            .ok_or(CurrencyError::Overflow)?;
+        if self.coins(from) < amount {
+            return Err(CurrencyError::InsufficientFunds {
+                required: copper,
+                available: self.coins(from) * from.value_in_copper(),
+            });
+        }
+
+        if !copper.is_multiple_of(to.value_in_copper()) {
+            return Err(CurrencyError::UnevenExchange);
+        }
+
+        let received = copper / to.value_in_copper();
+        *self.coins_mut(from) -= amount;
+        *self.coins_mut(to) += received;
+        self.record(0, description);
+        Ok(received)
+    }
+
+    // Splits the purse into equal shares, leaving whatever doesn't divide evenly behind.
+    pub fn split(&mut self, shares: u32, description: &str) -> Vec<Purse> {
+        if shares == 0 {
+            return vec![];
+        }
+
+        let share = self.total_in_copper() / shares;
+        if share == 0 {
+            return (0..shares).map(|_| Purse::default()).collect();
+        }
+        self.pay(share * shares, description).unwrap();
+
+        (0..shares)
+            .map(|_| {
+                let mut purse = Purse::default();
+                purse.add_change(share);
+                purse.record(share as i64, description);
+                purse
+            })
+            .collect()
+    }
+
+    // Moves all coins from another purse into this one, checked like a deposit.
+    pub fn merge(&mut self, other: Purse, description: &str) -> Result<(), CurrencyError> {
+        let copper = other.total_in_copper();
+        if self.total_in_copper().checked_add(copper).is_none() {
+            return Err(CurrencyError::Overflow);
+        }
+
+        for denomination in Denomination::ALL {
+            *self.coins_mut(denomination) += other.coins(denomination);
+        }
+        self.record(copper as i64, description);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pay_makes_change() {
+        let mut purse = Purse::default();
+        purse.deposit(Denomination::Gold, 2, "Loot").unwrap();
+        purse.deposit(Denomination::Copper, 3, "Loot").unwrap();
+
+        purse.pay(15, "Ale").unwrap();
+        assert_eq!(purse.total_in_copper(), 188);
+        assert_eq!(purse.gold, 1);
+        assert_eq!(purse.silver, 8);
+        assert_eq!(purse.copper, 8);
+
+        assert_eq!(
+            purse.pay(500, "Horse"),
+            Err(CurrencyError::InsufficientFunds {
+                required: 500,
+                available: 188
+            })
+        );
+        assert_eq!(purse.transactions.len(), 3);
+        assert_eq!(purse.transactions[2].amount, -15);
+    }
+
+    #[test]
+    fn test_exchange_and_split() {
+        let mut purse = Purse::default();
+        purse.deposit(Denomination::Copper, 250, "Hoard").unwrap();
+        assert_eq!(
+            purse.exchange(Denomination::Copper, Denomination::Gold, 150, "Bank"),
+            Err(CurrencyError::UnevenExchange)
+        );
+        assert_eq!(
+            purse.exchange(Denomination::Copper, Denomination::Gold, 200, "Bank"),
+            Ok(2)
+        );
+        assert_eq!(purse.transactions.len(), 2);
+        assert_eq!(purse.transactions[1].amount, 0);
+        assert_eq!(purse.transactions[1].balance, 250);
+        assert_eq!(
+            purse.exchange(Denomination::Platinum, Denomination::Gold, u32::MAX, "Bank"),
+            Err(CurrencyError::Overflow)
+        );
+
+        purse.deposit(Denomination::Platinum, 1, "Hoard").unwrap();
+        let shares = purse.split(3, "Party split");
+        assert_eq!(shares.len(), 3);
+        assert!(shares.iter().all(|share| share.total_in_copper() == 416));
+        assert_eq!(purse.total_in_copper(), 2);
+    }
+
+    #[test]
+    fn test_deposit_overflow() {
+        let mut purse = Purse::default();
+        assert_eq!(
+            purse.deposit(Denomination::Platinum, u32::MAX / 100, "Dragon hoard"),
+            Err(CurrencyError::Overflow)
+        );
+        purse
+            .deposit(Denomination::Gold, u32::MAX / 100, "Dragon hoard")
+            .unwrap();
+        assert_eq!(
+            purse.deposit(Denomination::Copper, 100, "Tip"),
+            Err(CurrencyError::Overflow)
+        );
+        assert_eq!(purse.copper, 0);
+        assert_eq!(purse.transactions.len(), 1);
+
+        let mut share = Purse::default();
+        share.deposit(Denomination::Silver, 10, "Share").unwrap();
+        assert_eq!(
+            purse.merge(share, "Party split"),
+            Err(CurrencyError::Overflow)
+        );
+        assert_eq!(purse.silver, 0);
+        assert_eq!(purse.transactions.len(), 1);
+    }
+}
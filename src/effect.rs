@@ -1,7 +1,6 @@
 use serde::{Deserialize, Serialize};
-use serde_json;
 
-use crate::models::Dice;
+use crate::{items::ArmorClassFormula, models::Dice};
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum DamageKind {
@@ -27,7 +26,13 @@ pub struct Damage {
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-enum ConditionKind {
+pub struct Healing {
+    pub dice: Dice,
+    pub bonus: i32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ConditionKind {
     Blinded,
     Charmed,
     Deafened,
@@ -55,6 +60,8 @@ pub enum DurationUnit {
     Months,
     Years,
     Immediate,
+    // Lasts until removed, e.g. the benefits of a feat.
+    Permanent,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -65,8 +72,8 @@ pub struct Duration {
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Condition {
-    kind: ConditionKind,
-    duration: Duration,
+    pub kind: ConditionKind,
+    pub duration: Duration,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -100,11 +107,15 @@ pub enum EnhancementBuff {
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum BuffKind {
     Enhancement(EnhancementBuff),
+    // Flat bonus or penalty of the given amount, e.g. a Ring of Protection's +1 to AC.
+    Bonus(EnhancementBuff, i32),
+    // Alternative way of calculating AC, e.g. Mage Armor or natural armor.
+    ArmorClassFormula(ArmorClassFormula),
     Advantage,
     Disadvantage,
     BonusAction,
     Reaction,
-    Resistance,
+    Resistance(DamageKind),
     Immunity,
     Vulnerability,
 }
@@ -118,6 +129,7 @@ pub struct Buff {
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Effect {
     Damage(Damage),
+    Healing(Healing),
     Condition(Condition),
     Buff(Buff),
 }
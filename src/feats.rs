@@ -0,0 +1,112 @@
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    effect::BuffKind,
+    items::{ArmorCategory, Weapon, WeaponKind, WeaponProficiency, WeaponProperty},
+    models::{Race, SavingThrow, SkillType},
+};
+
+// Attack roll penalty and damage bonus of a power attack.
+pub const POWER_ATTACK_PENALTY: i32 = 5;
+pub const POWER_ATTACK_BONUS: i32 = 10;
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum FeatPrerequisite {
+    Ability(SavingThrow, u32),
+    ArmorProficiency(ArmorCategory),
+    // Any one of the races.
+    Race(Vec<Race>),
+}
+
+// Weapons a power attack can be made with.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum PowerAttack {
+    HeavyMelee,
+    Ranged,
+}
+
+impl PowerAttack {
+    pub fn allows(&self, weapon: &Weapon) -> bool {
+        match self {
+            PowerAttack::HeavyMelee => {
+                weapon.kind == WeaponKind::Melee && weapon.has_property(&WeaponProperty::Heavy)
+            }
+            PowerAttack::Ranged => weapon.kind == WeaponKind::Ranged,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum FeatReaction {
+    DefensiveDuelist,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum FeatEffect {
+    AbilityIncrease(SavingThrow, u32),
+    // +1 to one of the abilities, picked when taking the feat.
+    AbilityChoice(Vec<SavingThrow>),
+    // Proficiency in saving throws with the ability picked for `AbilityChoice`.
+    SavingThrowChoice,
+    ArmorProficiency(ArmorCategory),
+    WeaponProficiency(WeaponProficiency),
+    SkillChoices(usize),
+    HitPointsPerLevel(i32),
+    // Permanent buff applied through the same modifiers as spells and magic items.
+    Buff(BuffKind),
+    PowerAttack(PowerAttack),
+    Reaction(FeatReaction),
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Feat {
+    pub name: String,
+    pub description: String,
+    pub prerequisites: Vec<FeatPrerequisite>,
+    pub effects: Vec<FeatEffect>,
+}
+
+// Choices some feats need when they are taken.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct FeatChoices {
+    pub ability: Option<SavingThrow>,
+    pub skills: Vec<SkillType>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum FeatError {
+    UnknownFeat(String),
+    AlreadyTaken(String),
+    PrerequisiteNotMet(FeatPrerequisite),
+    InvalidAbilityChoice,
+    InvalidSkillChoices,
+    AbilityTooHigh(SavingThrow),
+}
+
+pub fn feats() -> &'static Vec<Feat> {
+    static FEATS: OnceLock<Vec<Feat>> = OnceLock::new();
+    FEATS.get_or_init(|| serde_json::from_str(include_str!("../data/feats.json")).unwrap())
+}
+
+pub fn feat(name: &str) -> Option<&'static Feat> {
+    feats().iter().find(|feat| feat.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feat_data() {
+        let infernal_constitution = feat("Infernal Constitution").unwrap();
+        assert_eq!(
+            infernal_constitution.prerequisites,
+            vec![FeatPrerequisite::Race(vec![Race::Tiefling])]
+        );
+        assert_eq!(infernal_constitution.effects.len(), 3);
+        assert!(feat("Great Weapon Master").is_some());
+        assert!(feat("Polearm Mistress").is_none());
+    }
+}
@@ -0,0 +1,727 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actions::Shape,
+    effect::{DamageKind, Effect},
+    map::AttackRange,
+    models::Dice,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum WeaponCategory {
+    Simple,
+    Martial,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum WeaponKind {
+    Melee,
+    Ranged,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum WeaponProperty {
+    Ammunition,
+    Finesse,
+    Heavy,
+    Light,
+    Loading,
+    Reach,
+    Thrown,
+    TwoHanded,
+    // Damage dice when wielded with two hands.
+    Versatile(Dice),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WeaponRange {
+    pub normal: u32,
+    pub long: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum WeaponProficiency {
+    Simple,
+    Martial,
+    Weapon(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum WeaponMode {
+    OneHanded,
+    TwoHanded,
+    // Bonus action attack with a light weapon in the other hand.
+    OffHand,
+    Thrown,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum WeaponError {
+    NotThrown(String),
+    NotLight(String),
+    RequiresTwoHands(String),
+    // Carries no more of the named ammunition or thrown weapon.
+    OutOfAmmunition(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Weapon {
+    pub name: String,
+    pub category: WeaponCategory,
+    pub kind: WeaponKind,
+    pub damage: Dice,
+    pub damage_kind: DamageKind,
+    pub properties: Vec<WeaponProperty>,
+    // Normal and long range for ranged and thrown weapons.
+    pub range: Option<WeaponRange>,
+    pub magic_bonus: i32,
+    pub requires_attunement: bool,
+    pub effects: Vec<Effect>,
+    // Name of the inventory stack the weapon fires, e.g. "Arrow" for a longbow.
+    pub ammunition: Option<String>,
+}
+
+impl Weapon {
+    pub fn new(
+        name: &str,
+        category: WeaponCategory,
+        kind: WeaponKind,
+        damage: Dice,
+        damage_kind: DamageKind,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            category,
+            kind,
+            damage,
+            damage_kind,
+            properties: vec![],
+            range: None,
+            magic_bonus: 0,
+            requires_attunement: false,
+            effects: vec![],
+            ammunition: None,
+        }
+    }
+
+    pub fn with_properties(mut self, properties: Vec<WeaponProperty>) -> Self {
+        self.properties = properties;
+        self
+    }
+
+    pub fn with_range(mut self, normal: u32, long: u32) -> Self {
+        self.range = Some(WeaponRange { normal, long });
+        self
+    }
+
+    pub fn with_magic_bonus(mut self, magic_bonus: i32) -> Self {
+        self.magic_bonus = magic_bonus;
+        self
+    }
+
+    pub fn with_ammunition(mut self, ammunition: &str) -> Self {
+        self.ammunition = Some(ammunition.to_string());
+        self
+    }
+
+    pub fn with_attunement(mut self, effects: Vec<Effect>) -> Self {
+        self.requires_attunement = true;
+        self.effects = effects;
+        self
+    }
+
+    pub fn has_property(&self, property: &WeaponProperty) -> bool {
+        self.properties
+            .iter()
+            .any(|p| std::mem::discriminant(p) == std::mem::discriminant(property))
+    }
+
+    pub fn versatile_damage(&self) -> Option<&Dice> {
+        self.properties.iter().find_map(|property| match property {
+            WeaponProperty::Versatile(dice) => Some(dice),
+            _ => None,
+        })
+    }
+
+    pub fn check_mode(&self, mode: WeaponMode) -> Result<(), WeaponError> {
+        match mode {
+            WeaponMode::Thrown if !self.has_property(&WeaponProperty::Thrown) => {
+                Err(WeaponError::NotThrown(self.name.clone()))
+            }
+            WeaponMode::OffHand if !self.has_property(&WeaponProperty::Light) => {
+                Err(WeaponError::NotLight(self.name.clone()))
+            }
+            WeaponMode::OneHanded | WeaponMode::OffHand
+                if self.has_property(&WeaponProperty::TwoHanded) =>
+            {
+                Err(WeaponError::RequiresTwoHands(self.name.clone()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn attack_range(&self, mode: WeaponMode) -> AttackRange {
+        match (self.kind, mode, self.range) {
+            (WeaponKind::Ranged, _, Some(range)) | (_, WeaponMode::Thrown, Some(range)) => {
+                AttackRange::Ranged {
+                    normal: range.normal,
+                    long: range.long,
+                }
+            }
+            _ if self.has_property(&WeaponProperty::Reach) => AttackRange::Melee { reach: 10 },
+            _ => AttackRange::Melee { reach: 5 },
+        }
+    }
+
+    pub fn damage_dice(&self, mode: WeaponMode) -> Dice {
+        match (mode, self.versatile_damage()) {
+            (WeaponMode::TwoHanded, Some(dice)) => dice.clone(),
+            _ => self.damage.clone(),
+        }
+    }
+
+    // Ranged weapons use Dexterity and finesse weapons use the better of Strength and
+    // Dexterity. Thrown melee weapons keep using Strength.
+    pub fn ability_modifier(&self, strength: i32, dexterity: i32) -> i32 {
+        if self.has_property(&WeaponProperty::Finesse) {
+            strength.max(dexterity)
+        } else if self.kind == WeaponKind::Ranged {
+            dexterity
+        } else {
+            strength
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ArmorCategory {
+    Light,
+    Medium,
+    Heavy,
+    Shield,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Armor {
+    pub name: String,
+    pub category: ArmorCategory,
+    // Base AC for body armor, or the AC bonus for a shield.
+    pub armor_class: i32,
+    pub strength_requirement: Option<u32>,
+    pub stealth_disadvantage: bool,
+    pub magic_bonus: i32,
+    pub requires_attunement: bool,
+    pub effects: Vec<Effect>,
+}
+
+impl Armor {
+    pub fn new(name: &str, category: ArmorCategory, armor_class: i32) -> Self {
+        Self {
+            name: name.to_string(),
+            category,
+            armor_class,
+            strength_requirement: None,
+            stealth_disadvantage: false,
+            magic_bonus: 0,
+            requires_attunement: false,
+            effects: vec![],
+        }
+    }
+
+    pub fn with_strength_requirement(mut self, strength: u32) -> Self {
+        self.strength_requirement = Some(strength);
+        self
+    }
+
+    pub fn with_stealth_disadvantage(mut self) -> Self {
+        self.stealth_disadvantage = true;
+        self
+    }
+
+    pub fn with_magic_bonus(mut self, magic_bonus: i32) -> Self {
+        self.magic_bonus = magic_bonus;
+        self
+    }
+
+    pub fn with_attunement(mut self, effects: Vec<Effect>) -> Self {
+        self.requires_attunement = true;
+        self.effects = effects;
+        self
+    }
+
+    pub fn max_dexterity_bonus(&self) -> Option<i32> {
+        match self.category {
+            ArmorCategory::Light => None,
+            ArmorCategory::Medium => Some(2),
+            ArmorCategory::Heavy | ArmorCategory::Shield => Some(0),
+        }
+    }
+
+    pub fn armor_class(&self, dexterity: i32) -> i32 {
+        let dexterity = match self.max_dexterity_bonus() {
+            Some(max) => dexterity.min(max),
+            None => dexterity,
+        };
+
+        self.armor_class + dexterity + self.magic_bonus
+    }
+}
+
+// Ways of calculating AC when not wearing body armor. A creature with several of them uses
+// whichever gives the highest AC.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ArmorClassFormula {
+    Unarmored,
+    // Barbarian: 10 + Dex + Con, shields allowed.
+    BarbarianUnarmoredDefense,
+    // Monk: 10 + Dex + Wis, only without a shield.
+    MonkUnarmoredDefense,
+    MageArmor,
+    NaturalArmor(i32),
+}
+
+impl ArmorClassFormula {
+    pub fn armor_class(
+        &self,
+        dexterity: i32,
+        constitution: i32,
+        wisdom: i32,
+        has_shield: bool,
+    ) -> Option<i32> {
+        match self {
+            ArmorClassFormula::Unarmored => Some(10 + dexterity),
+            ArmorClassFormula::BarbarianUnarmoredDefense => Some(10 + dexterity + constitution),
+            ArmorClassFormula::MonkUnarmoredDefense if !has_shield => Some(10 + dexterity + wisdom),
+            ArmorClassFormula::MonkUnarmoredDefense => None,
+            ArmorClassFormula::MageArmor => Some(13 + dexterity),
+            ArmorClassFormula::NaturalArmor(base) => Some(base + dexterity),
+        }
+    }
+}
+
+pub const MAX_ATTUNED_ITEMS: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EquipmentSlot {
+    MainHand,
+    OffHand,
+    Armor,
+    Shield,
+    Head,
+    Neck,
+    Cloak,
+    Hands,
+    Waist,
+    Feet,
+    LeftRing,
+    RightRing,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WondrousItem {
+    pub name: String,
+    pub slot: EquipmentSlot,
+    pub requires_attunement: bool,
+    pub effects: Vec<Effect>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Equippable {
+    Weapon(Weapon),
+    Armor(Armor),
+    Wondrous(WondrousItem),
+}
+
+impl Equippable {
+    pub fn name(&self) -> &str {
+        match self {
+            Equippable::Weapon(weapon) => &weapon.name,
+            Equippable::Armor(armor) => &armor.name,
+            Equippable::Wondrous(item) => &item.name,
+        }
+    }
+
+    pub fn requires_attunement(&self) -> bool {
+        match self {
+            Equippable::Weapon(weapon) => weapon.requires_attunement,
+            Equippable::Armor(armor) => armor.requires_attunement,
+            Equippable::Wondrous(item) => item.requires_attunement,
+        }
+    }
+
+    pub fn effects(&self) -> &[Effect] {
+        match self {
+            Equippable::Weapon(weapon) => &weapon.effects,
+            Equippable::Armor(armor) => &armor.effects,
+            Equippable::Wondrous(item) => &item.effects,
+        }
+    }
+
+    pub fn fits(&self, slot: EquipmentSlot) -> bool {
+        match self {
+            Equippable::Weapon(_) => {
+                matches!(slot, EquipmentSlot::MainHand | EquipmentSlot::OffHand)
+            }
+            Equippable::Armor(armor) if armor.category == ArmorCategory::Shield => {
+                slot == EquipmentSlot::Shield
+            }
+            Equippable::Armor(_) => slot == EquipmentSlot::Armor,
+            Equippable::Wondrous(item) => match item.slot {
+                EquipmentSlot::LeftRing | EquipmentSlot::RightRing => {
+                    matches!(slot, EquipmentSlot::LeftRing | EquipmentSlot::RightRing)
+                }
+                item_slot => item_slot == slot,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EquipError {
+    WrongSlot(EquipmentSlot),
+    HandsFull,
+    NotProficient(String),
+    NotEquipped(String),
+    NotCarried(String),
+    AlreadyAttuned(String),
+    AttunementNotRequired(String),
+    TooManyAttunedItems,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Equipment {
+    pub main_hand: Option<Weapon>,
+    pub off_hand: Option<Weapon>,
+    pub armor: Option<Armor>,
+    pub shield: Option<Armor>,
+    pub head: Option<WondrousItem>,
+    pub neck: Option<WondrousItem>,
+    pub cloak: Option<WondrousItem>,
+    pub hands: Option<WondrousItem>,
+    pub waist: Option<WondrousItem>,
+    pub feet: Option<WondrousItem>,
+    pub left_ring: Option<WondrousItem>,
+    pub right_ring: Option<WondrousItem>,
+    // Names of the magic items the wearer is attuned to.
+    pub attuned: Vec<String>,
+}
+
+impl Equipment {
+    pub fn get(&self, slot: EquipmentSlot) -> Option<Equippable> {
+        match slot {
+            EquipmentSlot::MainHand => self.main_hand.clone().map(Equippable::Weapon),
+            EquipmentSlot::OffHand => self.off_hand.clone().map(Equippable::Weapon),
+            EquipmentSlot::Armor => self.armor.clone().map(Equippable::Armor),
+            EquipmentSlot::Shield => self.shield.clone().map(Equippable::Armor),
+            EquipmentSlot::Head => self.head.clone().map(Equippable::Wondrous),
+            EquipmentSlot::Neck => self.neck.clone().map(Equippable::Wondrous),
+            EquipmentSlot::Cloak => self.cloak.clone().map(Equippable::Wondrous),
+            EquipmentSlot::Hands => self.hands.clone().map(Equippable::Wondrous),
+            EquipmentSlot::Waist => self.waist.clone().map(Equippable::Wondrous),
+            EquipmentSlot::Feet => self.feet.clone().map(Equippable::Wondrous),
+            EquipmentSlot::LeftRing => self.left_ring.clone().map(Equippable::Wondrous),
+            EquipmentSlot::RightRing => self.right_ring.clone().map(Equippable::Wondrous),
+        }
+    }
+
+    pub fn items(&self) -> Vec<Equippable> {
+        [
+            EquipmentSlot::MainHand,
+            EquipmentSlot::OffHand,
+            EquipmentSlot::Armor,
+            EquipmentSlot::Shield,
+            EquipmentSlot::Head,
+            EquipmentSlot::Neck,
+            EquipmentSlot::Cloak,
+            EquipmentSlot::Hands,
+            EquipmentSlot::Waist,
+            EquipmentSlot::Feet,
+            EquipmentSlot::LeftRing,
+            EquipmentSlot::RightRing,
+        ]
+        .into_iter()
+        .filter_map(|slot| self.get(slot))
+        .collect()
+    }
+
+    fn two_handed_in_main_hand(&self) -> bool {
+        self.main_hand
+            .as_ref()
+            .is_some_and(|weapon| weapon.has_property(&WeaponProperty::TwoHanded))
+    }
+
+    // A creature has two hands: a two-handed weapon takes both, and a shield or a second
+    // weapon takes the off hand.
+    fn check_hands(&self, item: &Equippable, slot: EquipmentSlot) -> Result<(), EquipError> {
+        let hands_full = match (item, slot) {
+            (Equippable::Weapon(weapon), EquipmentSlot::MainHand)
+                if weapon.has_property(&WeaponProperty::TwoHanded) =>
+            {
+                self.off_hand.is_some() || self.shield.is_some()
+            }
+            (Equippable::Weapon(weapon), EquipmentSlot::OffHand) => {
+                weapon.has_property(&WeaponProperty::TwoHanded)
+                    || self.shield.is_some()
+                    || self.two_handed_in_main_hand()
+            }
+            (_, EquipmentSlot::Shield) => self.off_hand.is_some() || self.two_handed_in_main_hand(),
+            _ => false,
+        };
+
+        if hands_full {
+            Err(EquipError::HandsFull)
+        } else {
+            Ok(())
+        }
+    }
+
+    // Puts an item in a slot and returns whatever was there before.
+    pub fn equip(
+        &mut self,
+        item: Equippable,
+        slot: EquipmentSlot,
+    ) -> Result<Option<Equippable>, EquipError> {
+        if !item.fits(slot) {
+            return Err(EquipError::WrongSlot(slot));
+        }
+        self.check_hands(&item, slot)?;
+
+        let previous = self.unequip(slot);
+        match (item, slot) {
+            (Equippable::Weapon(weapon), EquipmentSlot::MainHand) => self.main_hand = Some(weapon),
+            (Equippable::Weapon(weapon), _) => self.off_hand = Some(weapon),
+            (Equippable::Armor(armor), EquipmentSlot::Shield) => self.shield = Some(armor),
+            (Equippable::Armor(armor), _) => self.armor = Some(armor),
+            (Equippable::Wondrous(item), slot) => {
+                let target = match slot {
+                    EquipmentSlot::Head => &mut self.head,
+                    EquipmentSlot::Neck => &mut self.neck,
+                    EquipmentSlot::Cloak => &mut self.cloak,
+                    EquipmentSlot::Hands => &mut self.hands,
+                    EquipmentSlot::Waist => &mut self.waist,
+                    EquipmentSlot::Feet => &mut self.feet,
+                    EquipmentSlot::LeftRing => &mut self.left_ring,
+                    _ => &mut self.right_ring,
+                };
+                *target = Some(item);
+            }
+        }
+
+        Ok(previous)
+    }
+
+    pub fn unequip(&mut self, slot: EquipmentSlot) -> Option<Equippable> {
+        match slot {
+            EquipmentSlot::MainHand => self.main_hand.take().map(Equippable::Weapon),
+            EquipmentSlot::OffHand => self.off_hand.take().map(Equippable::Weapon),
+            EquipmentSlot::Armor => self.armor.take().map(Equippable::Armor),
+            EquipmentSlot::Shield => self.shield.take().map(Equippable::Armor),
+            EquipmentSlot::Head => self.head.take().map(Equippable::Wondrous),
+            EquipmentSlot::Neck => self.neck.take().map(Equippable::Wondrous),
+            EquipmentSlot::Cloak => self.cloak.take().map(Equippable::Wondrous),
+            EquipmentSlot::Hands => self.hands.take().map(Equippable::Wondrous),
+            EquipmentSlot::Waist => self.waist.take().map(Equippable::Wondrous),
+            EquipmentSlot::Feet => self.feet.take().map(Equippable::Wondrous),
+            EquipmentSlot::LeftRing => self.left_ring.take().map(Equippable::Wondrous),
+            EquipmentSlot::RightRing => self.right_ring.take().map(Equippable::Wondrous),
+        }
+    }
+
+    pub fn attune(&mut self, name: &str) -> Result<(), EquipError> {
+        let item = self
+            .items()
+            .into_iter()
+            .find(|item| item.name() == name)
+            .ok_or_else(|| EquipError::NotEquipped(name.to_string()))?;
+
+        if !item.requires_attunement() {
+            return Err(EquipError::AttunementNotRequired(name.to_string()));
+        }
+        if self.attuned.iter().any(|attuned| attuned == name) {
+            return Err(EquipError::AlreadyAttuned(name.to_string()));
+        }
+        if self.attuned.len() >= MAX_ATTUNED_ITEMS {
+            return Err(EquipError::TooManyAttunedItems);
+        }
+
+        self.attuned.push(name.to_string());
+        Ok(())
+    }
+
+    pub fn end_attunement(&mut self, name: &str) -> bool {
+        let attuned = self.attuned.len();
+        self.attuned.retain(|attuned| attuned != name);
+        self.attuned.len() != attuned
+    }
+
+    // Effects of equipped items, skipping magic items the wearer isn't attuned to.
+    pub fn effects(&self) -> Vec<Effect> {
+        self.items()
+            .iter()
+            .filter(|item| {
+                !item.requires_attunement() || self.attuned.iter().any(|name| name == item.name())
+            })
+            .flat_map(|item| item.effects().to_vec())
+            .collect()
+    }
+}
+
+// Spell stored on a scroll, cast by whoever reads it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpellScroll {
+    pub spell: String,
+    pub effect: Effect,
+    pub shape: Shape,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weapon_modes() {
+        let longsword = Weapon::new(
+            "Longsword",
+            WeaponCategory::Martial,
+            WeaponKind::Melee,
+            Dice { count: 1, sides: 8 },
+            DamageKind::Slashing,
+        )
+        .with_properties(vec![WeaponProperty::Versatile(Dice {
+            count: 1,
+            sides: 10,
+        })]);
+        let dagger = Weapon::new(
+            "Dagger",
+            WeaponCategory::Simple,
+            WeaponKind::Melee,
+            Dice { count: 1, sides: 4 },
+            DamageKind::Piercing,
+        )
+        .with_properties(vec![
+            WeaponProperty::Finesse,
+            WeaponProperty::Light,
+            WeaponProperty::Thrown,
+        ])
+        .with_range(20, 60);
+
+        assert_eq!(
+            longsword.damage_dice(WeaponMode::TwoHanded),
+            Dice {
+                count: 1,
+                sides: 10
+            }
+        );
+        assert_eq!(
+            longsword.damage_dice(WeaponMode::OneHanded),
+            Dice { count: 1, sides: 8 }
+        );
+        assert_eq!(
+            longsword.check_mode(WeaponMode::Thrown),
+            Err(WeaponError::NotThrown(String::from("Longsword")))
+        );
+        assert_eq!(longsword.ability_modifier(3, 1), 3);
+
+        assert_eq!(dagger.check_mode(WeaponMode::OffHand), Ok(()));
+        assert_eq!(dagger.ability_modifier(0, 3), 3);
+        assert_eq!(
+            dagger.attack_range(WeaponMode::Thrown),
+            AttackRange::Ranged {
+                normal: 20,
+                long: 60
+            }
+        );
+        assert_eq!(
+            dagger.attack_range(WeaponMode::OneHanded),
+            AttackRange::Melee { reach: 5 }
+        );
+    }
+
+    #[test]
+    fn test_armor_dexterity_caps() {
+        let leather = Armor::new("Leather", ArmorCategory::Light, 11);
+        let half_plate = Armor::new("Half Plate", ArmorCategory::Medium, 15)
+            .with_stealth_disadvantage()
+            .with_magic_bonus(1);
+        let plate = Armor::new("Plate", ArmorCategory::Heavy, 18).with_strength_requirement(15);
+
+        assert_eq!(leather.armor_class(4), 15);
+        assert_eq!(half_plate.armor_class(4), 18);
+        assert_eq!(half_plate.armor_class(-1), 15);
+        assert_eq!(plate.armor_class(4), 18);
+        assert_eq!(
+            ArmorClassFormula::MonkUnarmoredDefense.armor_class(3, 0, 2, true),
+            None
+        );
+    }
+
+    #[test]
+    fn test_equip_checks_hands_and_attunement() {
+        let mut equipment = Equipment::default();
+        let greatsword = Weapon::new(
+            "Greatsword",
+            WeaponCategory::Martial,
+            WeaponKind::Melee,
+            Dice { count: 2, sides: 6 },
+            DamageKind::Slashing,
+        )
+        .with_properties(vec![WeaponProperty::Heavy, WeaponProperty::TwoHanded]);
+        let shield = Armor::new("Shield", ArmorCategory::Shield, 2);
+
+        equipment
+            .equip(Equippable::Weapon(greatsword), EquipmentSlot::MainHand)
+            .unwrap();
+        assert_eq!(
+            equipment.equip(Equippable::Armor(shield.clone()), EquipmentSlot::Shield),
+            Err(EquipError::HandsFull)
+        );
+        assert_eq!(
+            equipment.equip(Equippable::Armor(shield), EquipmentSlot::Armor),
+            Err(EquipError::WrongSlot(EquipmentSlot::Armor))
+        );
+
+        let ring = |name: &str| WondrousItem {
+            name: name.to_string(),
+            slot: EquipmentSlot::LeftRing,
+            requires_attunement: true,
+            effects: vec![],
+        };
+        let cloak = WondrousItem {
+            name: String::from("Cloak of Protection"),
+            slot: EquipmentSlot::Cloak,
+            requires_attunement: true,
+            effects: vec![],
+        };
+        let boots = WondrousItem {
+            name: String::from("Winged Boots"),
+            slot: EquipmentSlot::Feet,
+            requires_attunement: true,
+            effects: vec![],
+        };
+        for (item, slot) in [
+            (ring("Ring of Protection"), EquipmentSlot::LeftRing),
+            (ring("Ring of Evasion"), EquipmentSlot::RightRing),
+            (cloak, EquipmentSlot::Cloak),
+            (boots, EquipmentSlot::Feet),
+        ] {
+            equipment.equip(Equippable::Wondrous(item), slot).unwrap();
+        }
+
+        equipment.attune("Ring of Protection").unwrap();
+        equipment.attune("Ring of Evasion").unwrap();
+        equipment.attune("Cloak of Protection").unwrap();
+        assert_eq!(
+            equipment.attune("Winged Boots"),
+            Err(EquipError::TooManyAttunedItems)
+        );
+        assert_eq!(
+            equipment.attune("Greatsword"),
+            Err(EquipError::AttunementNotRequired(String::from(
+                "Greatsword"
+            )))
+        );
+
+        assert!(equipment.end_attunement("Ring of Evasion"));
+        assert_eq!(equipment.attune("Winged Boots"), Ok(()));
+    }
+}
@@ -0,0 +1,356 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    builder::MAX_ABILITY_SCORE,
+    classes::{ClassFeature, FeatureError, FightingStyle, MulticlassError},
+    feats::{FeatChoices, FeatEffect, FeatError},
+    models::{Character, Class, Dice, SavingThrow, SkillType, Skills},
+    races::race_definition,
+};
+
+pub const MAX_LEVEL: u32 = 20;
+
+// Experience needed to reach each level, starting with level 1.
+pub const XP_THRESHOLDS: [u32; 20] = [
+    0, 300, 900, 2700, 6500, 14000, 23000, 34000, 48000, 64000, 85000, 100000, 120000, 140000,
+    165000, 195000, 225000, 265000, 305000, 355000,
+];
+
+pub fn level_for_experience(experience: u32) -> u32 {
+    XP_THRESHOLDS
+        .iter()
+        .filter(|threshold| **threshold <= experience)
+        .count() as u32
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum Advancement {
+    Experience(u32),
+    // Levels are awarded at story milestones instead of earned with experience.
+    Milestone { level: u32 },
+}
+
+impl Advancement {
+    // Level the character has earned and may level up to.
+    pub fn level(&self) -> u32 {
+        match self {
+            Advancement::Experience(experience) => level_for_experience(*experience),
+            Advancement::Milestone { level } => *level,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum HitPointIncrease {
+    Roll,
+    // Half the hit die plus one, e.g. 6 for a d10.
+    Average,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum AbilityScoreImprovement {
+    // +1 to each ability; naming the same ability twice gives it +2.
+    Increase(SavingThrow, SavingThrow),
+    Feat(String, FeatChoices),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum LevelUpError {
+    LevelNotEarned,
+    Multiclass(MulticlassError),
+    Feature(FeatureError),
+    MissingSubclass,
+    MissingFightingStyle,
+    // Levels with the Ability Score Improvement feature need an increase or a feat, and
+    // other levels can't take one.
+    MissingImprovement,
+    NoImprovementAvailable,
+    AbilityTooHigh(SavingThrow),
+    Feat(FeatError),
+    // New spells must be the number the class learns at this level, and not already known.
+    InvalidSpellChoices,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct LevelUp {
+    class: Class,
+    hit_points: HitPointIncrease,
+    skills: Vec<SkillType>,
+    subclass: Option<String>,
+    fighting_style: Option<FightingStyle>,
+    improvement: Option<AbilityScoreImprovement>,
+    spells: Vec<String>,
+}
+
+impl LevelUp {
+    pub fn new(class: Class) -> Self {
+        Self {
+            class,
+            hit_points: HitPointIncrease::Average,
+            skills: vec![],
+            subclass: None,
+            fighting_style: None,
+            improvement: None,
+            spells: vec![],
+        }
+    }
+
+    pub fn with_hit_points(mut self, hit_points: HitPointIncrease) -> Self {
+        self.hit_points = hit_points;
+        self
+    }
+
+    // Skills picked when the level is the first in a new class.
+    pub fn with_skills(mut self, skills: Vec<SkillType>) -> Self {
+        self.skills = skills;
+        self
+    }
+
+    pub fn with_subclass(mut self, subclass: &str) -> Self {
+        self.subclass = Some(subclass.to_string());
+        self
+    }
+
+    pub fn with_fighting_style(mut self, style: FightingStyle) -> Self {
+        self.fighting_style = Some(style);
+        self
+    }
+
+    pub fn with_improvement(mut self, improvement: AbilityScoreImprovement) -> Self {
+        self.improvement = Some(improvement);
+        self
+    }
+
+    pub fn with_spells(mut self, spells: Vec<String>) -> Self {
+        self.spells = spells;
+        self
+    }
+
+    // Gains a level in the class and returns the features it unlocks. The character is left
+    // untouched if any choice is missing or invalid.
+    pub fn apply(
+        self,
+        character: &mut Character,
+    ) -> Result<Vec<&'static ClassFeature>, LevelUpError> {
+        if character.advancement.level() <= character.level() {
+            return Err(LevelUpError::LevelNotEarned);
+        }
+
+        let mut leveled = character.clone();
+        leveled
+            .add_class_level(&self.class, &self.skills)
+            .map_err(LevelUpError::Multiclass)?;
+
+        if let Some(subclass) = &self.subclass {
+            leveled
+                .choose_subclass(&self.class, subclass)
+                .map_err(LevelUpError::Feature)?;
+        }
+        if let Some(style) = self.fighting_style {
+            leveled
+                .choose_fighting_style(&self.class, style)
+                .map_err(LevelUpError::Feature)?;
+        }
+
+        let details = leveled.class_details(&self.class).unwrap().clone();
+        let definition = details.definition();
+        if details.subclass.is_none() && details.level >= definition.subclass_level {
+            return Err(LevelUpError::MissingSubclass);
+        }
+        if details.fighting_styles.len() < details.fighting_style_choices() {
+            return Err(LevelUpError::MissingFightingStyle);
+        }
+
+        let features: Vec<&'static ClassFeature> = details
+            .features()
+            .filter(|feature| feature.level == details.level)
+            .collect();
+
+        let constitution = leveled.abilities.constitution.get_modifier();
+        let improvement_available = features
+            .iter()
+            .any(|feature| feature.name == "Ability Score Improvement");
+        // Constitution modifier the new level's hit points are rolled with.
+        let new_constitution = match (self.improvement, improvement_available) {
+            (Some(AbilityScoreImprovement::Increase(first, second)), true) => {
+                for ability in [first, second] {
+                    let score = leveled.abilities.get_mut(&ability);
+                    if score.value >= MAX_ABILITY_SCORE {
+                        return Err(LevelUpError::AbilityTooHigh(ability));
+                    }
+                    score.value += 1;
+                }
+                leveled.skills = Skills::from_abilities(&leveled.abilities);
+                leveled.abilities.constitution.get_modifier()
+            }
+            // The feat raises the hit points of every level, this one included.
+            (Some(AbilityScoreImprovement::Feat(feat, choices)), true) => {
+                leveled
+                    .take_feat(&feat, &choices)
+                    .map_err(LevelUpError::Feat)?;
+                constitution
+            }
+            (None, true) => return Err(LevelUpError::MissingImprovement),
+            (Some(_), false) => return Err(LevelUpError::NoImprovementAvailable),
+            (None, false) => constitution,
+        };
+
+        let new_spells = details.spellcasting().map_or(0, |spellcasting| {
+            spellcasting.spells_known(details.level) - spellcasting.spells_known(details.level - 1)
+        });
+        let spells_are_valid = self.spells.len() == new_spells as usize
+            && self.spells.iter().enumerate().all(|(i, spell)| {
+                !details.spells_known.contains(spell) && !self.spells[..i].contains(spell)
+            });
+        if !spells_are_valid {
+            return Err(LevelUpError::InvalidSpellChoices);
+        }
+
+        // A higher Constitution modifier raises the hit points of earlier levels too.
+        let earlier_levels = character.level() as i32;
+        let die = details.hit_dice as i32;
+        let roll = match self.hit_points {
+            HitPointIncrease::Roll => {
+                Dice {
+                    count: 1,
+                    sides: die,
+                }
+                .roll()
+                .total
+            }
+            HitPointIncrease::Average => die / 2 + 1,
+        };
+        let hit_points = (roll + new_constitution).max(1)
+            + (new_constitution - constitution) * earlier_levels
+            // A feat taken at this level has already added its hit points for every level.
+            + hit_points_per_level(character);
+        leveled.hit_points.max += hit_points;
+        leveled.hit_points.current += hit_points;
+
+        let details = leveled
+            .classes
+            .iter_mut()
+            .find(|details| details.kind == self.class)
+            .unwrap();
+        details.spells_known.extend(self.spells);
+        leveled.update_armor_class();
+
+        *character = leveled;
+        Ok(features)
+    }
+}
+
+// Extra hit points some races and feats give every level, e.g. a hill dwarf's or Tough's.
+fn hit_points_per_level(character: &Character) -> i32 {
+    let racial = character
+        .subrace
+        .as_ref()
+        .and_then(|subrace| race_definition(&character.race).subrace(subrace))
+        .map_or(0, |subrace| subrace.hit_points_per_level);
+    let feats: i32 = character
+        .feat_effects()
+        .iter()
+        .map(|effect| match effect {
+            FeatEffect::HitPointsPerLevel(amount) => *amount,
+            _ => 0,
+        })
+        .sum();
+    racial + feats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        builder::{AbilityScores, CharacterBuilder},
+        models::Race,
+    };
+
+    #[test]
+    fn test_level_up() {
+        assert_eq!(level_for_experience(0), 1);
+        assert_eq!(level_for_experience(899), 2);
+        assert_eq!(level_for_experience(355000), 20);
+
+        let mut character = CharacterBuilder::new("Bruenor")
+            .with_race(Race::Dwarf)
+            .with_subrace("Hill Dwarf")
+            .with_class(Class::Fighter)
+            .with_level(3)
+            .with_class_skills(vec![SkillType::Athletics, SkillType::Survival])
+            .with_subclass("Champion")
+            .with_fighting_style(FightingStyle::Defense)
+            .with_ability_scores(AbilityScores::PointBuy([15, 10, 14, 8, 12, 10]))
+            .build(1)
+            .unwrap();
+        assert_eq!(character.advancement, Advancement::Experience(900));
+        assert_eq!(
+            LevelUp::new(Class::Fighter).apply(&mut character),
+            Err(LevelUpError::LevelNotEarned)
+        );
+
+        character.gain_experience(1800);
+        let before = character.clone();
+        assert_eq!(
+            LevelUp::new(Class::Fighter).apply(&mut character),
+            Err(LevelUpError::MissingImprovement)
+        );
+        assert_eq!(character, before);
+
+        // Constitution 16 becomes 17 but the modifier stays +3.
+        let features = LevelUp::new(Class::Fighter)
+            .with_improvement(AbilityScoreImprovement::Increase(
+                SavingThrow::Strength,
+                SavingThrow::Constitution,
+            ))
+            .apply(&mut character)
+            .unwrap();
+        assert_eq!(features[0].name, "Ability Score Improvement");
+        assert_eq!(character.level(), 4);
+        assert_eq!(character.abilities.strength.value, 16);
+        assert_eq!(character.hit_points.max, before.hit_points.max + 6 + 3 + 1);
+
+        // Tough adds 2 hit points for each of the six levels on top of the usual increase.
+        character.gain_experience(14000 - 2700);
+        LevelUp::new(Class::Fighter).apply(&mut character).unwrap();
+        let before = character.clone();
+        LevelUp::new(Class::Fighter)
+            .with_improvement(AbilityScoreImprovement::Feat(
+                String::from("Tough"),
+                FeatChoices::default(),
+            ))
+            .apply(&mut character)
+            .unwrap();
+        assert_eq!(character.feats, vec![String::from("Tough")]);
+        assert_eq!(character.hit_points.max, before.hit_points.max + 10 + 12);
+
+        let mut milestone = CharacterBuilder::new("Merric")
+            .with_race(Race::Halfling)
+            .with_subrace("Lightfoot")
+            .with_class(Class::Sorcerer)
+            .with_class_skills(vec![SkillType::Arcana, SkillType::Insight])
+            .with_subclass("Draconic Bloodline")
+            .with_ability_scores(AbilityScores::StandardArray([8, 14, 13, 12, 10, 15]))
+            .with_milestones()
+            .build(2)
+            .unwrap();
+        milestone.reach_milestone();
+        let level_up = LevelUp::new(Class::Sorcerer).with_hit_points(HitPointIncrease::Roll);
+        assert_eq!(
+            level_up.clone().apply(&mut milestone),
+            Err(LevelUpError::InvalidSpellChoices)
+        );
+        level_up
+            .with_spells(vec![String::from("Shield")])
+            .apply(&mut milestone)
+            .unwrap();
+        assert_eq!(milestone.spell_slots(), [3, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(
+            milestone
+                .class_details(&Class::Sorcerer)
+                .unwrap()
+                .spells_known,
+            vec![String::from("Shield")]
+        );
+    }
+}
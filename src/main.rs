@@ -1,11 +1,31 @@
-use crate::state::TerminalInterface;
+use std::process;
+
+use clap::Parser;
+
+use crate::cli::Cli;
 
 pub mod actions;
+pub mod backgrounds;
+pub mod builder;
+pub mod classes;
+pub mod cli;
+pub mod currency;
 pub mod effect;
+pub mod feats;
+pub mod items;
+pub mod leveling;
+pub mod map;
 pub mod models;
+pub mod races;
+pub mod reactions;
+pub mod resources;
+pub mod rest;
+pub mod save;
 pub mod state;
 
 fn main() {
-    println!("Volo's Virtual Toolkit");
-    TerminalInterface::new().run();
+    if let Err(error) = Cli::parse().run() {
+        eprintln!("Error: {:?}", error);
+        process::exit(1);
+    }
 }
@@ -0,0 +1,642 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::Size;
+
+pub const SQUARE_FEET: u32 = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Position {
+    pub fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    pub fn is_adjacent(&self, other: &Position) -> bool {
+        self != other && (self.x - other.x).abs() <= 1 && (self.y - other.y).abs() <= 1
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DiagonalRule {
+    // Every diagonal square costs 5 feet.
+    Standard,
+    // Optional rule: diagonals alternate between 5 and 10 feet.
+    Alternating,
+    // Straight-line distance, useful for continuous maps.
+    Euclidean,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Terrain {
+    Normal,
+    Difficult,
+    // Blocks movement and line of sight.
+    Wall,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Cover {
+    None,
+    Half,
+    ThreeQuarters,
+    // Target can't be targeted directly by an attack or a spell.
+    Total,
+}
+
+impl Cover {
+    // Bonus to AC and Dexterity saving throws.
+    pub fn bonus(&self) -> i32 {
+        match self {
+            Cover::None | Cover::Total => 0,
+            Cover::Half => 2,
+            Cover::ThreeQuarters => 5,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AttackRange {
+    Melee { reach: u32 },
+    Ranged { normal: u32, long: u32 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum RangeCheck {
+    InRange,
+    // Target is beyond normal range, so the attack roll has disadvantage.
+    LongRange,
+    OutOfRange,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Placement {
+    pub position: Position,
+    pub size: Size,
+    pub movement_remaining: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MoveError {
+    NotOnMap,
+    OutOfBounds(Position),
+    Blocked(Position),
+    NotAdjacent(Position),
+    Occupied(Position),
+    InsufficientMovement { required: u32, remaining: u32 },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BattleMap {
+    pub width: i32,
+    pub height: i32,
+    pub diagonal_rule: DiagonalRule,
+    #[serde(with = "square_entries")]
+    terrain: HashMap<Position, Terrain>,
+    placements: HashMap<u32, Placement>,
+}
+
+impl BattleMap {
+    pub fn new(width: i32, height: i32) -> Self {
+        Self {
+            width,
+            height,
+            diagonal_rule: DiagonalRule::Standard,
+            terrain: HashMap::new(),
+            placements: HashMap::new(),
+        }
+    }
+
+    pub fn in_bounds(&self, position: Position) -> bool {
+        position.x >= 0 && position.y >= 0 && position.x < self.width && position.y < self.height
+    }
+
+    pub fn set_terrain(&mut self, position: Position, terrain: Terrain) {
+        match terrain {
+            Terrain::Normal => self.terrain.remove(&position),
+            _ => self.terrain.insert(position, terrain),
+        };
+    }
+
+    pub fn terrain_at(&self, position: Position) -> Terrain {
+        self.terrain
+            .get(&position)
+            .copied()
+            .unwrap_or(Terrain::Normal)
+    }
+
+    pub fn place(&mut self, id: u32, position: Position, size: Size) -> Result<(), MoveError> {
+        for square in footprint(position, &size) {
+            if !self.in_bounds(square) {
+                return Err(MoveError::OutOfBounds(square));
+            }
+            if self.terrain_at(square) == Terrain::Wall {
+                return Err(MoveError::Blocked(square));
+            }
+            if self.occupant(square).is_some_and(|other| other != id) {
+                return Err(MoveError::Occupied(square));
+            }
+        }
+
+        self.placements.insert(
+            id,
+            Placement {
+                position,
+                size,
+                movement_remaining: 0,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn remove(&mut self, id: u32) -> Option<Placement> {
+        self.placements.remove(&id)
+    }
+
+    pub fn placement(&self, id: u32) -> Option<&Placement> {
+        self.placements.get(&id)
+    }
+
+    pub fn position_of(&self, id: u32) -> Option<Position> {
+        self.placements.get(&id).map(|placement| placement.position)
+    }
+
+    pub fn occupant(&self, square: Position) -> Option<u32> {
+        self.placements.iter().find_map(|(id, placement)| {
+            footprint(placement.position, &placement.size)
+                .contains(&square)
+                .then_some(*id)
+        })
+    }
+
+    pub fn start_turn(&mut self, id: u32, speed: u32) {
+        if let Some(placement) = self.placements.get_mut(&id) {
+            placement.movement_remaining = speed;
+        }
+    }
+
+    pub fn dash(&mut self, id: u32, speed: u32) {
+        if let Some(placement) = self.placements.get_mut(&id) {
+            placement.movement_remaining += speed;
+        }
+    }
+
+    // Validates a move square by square along `path` and returns the feet spent on each step.
+    // Difficult terrain costs an extra 5 feet per square entered. Creatures can't move through
+    // a hostile creature's square, and an ally's square counts as difficult terrain.
+    pub fn path_costs(
+        &self,
+        id: u32,
+        path: &[Position],
+        is_hostile: impl Fn(u32) -> bool,
+    ) -> Result<Vec<u32>, MoveError> {
+        let placement = self.placements.get(&id).ok_or(MoveError::NotOnMap)?;
+        let size = &placement.size;
+        let remaining = placement.movement_remaining;
+
+        let mut current = placement.position;
+        let mut diagonals = 0;
+        let mut costs = vec![];
+        for step in path {
+            if !current.is_adjacent(step) {
+                return Err(MoveError::NotAdjacent(*step));
+            }
+            for square in footprint(*step, size) {
+                if !self.in_bounds(square) {
+                    return Err(MoveError::OutOfBounds(square));
+                }
+                if self.terrain_at(square) == Terrain::Wall {
+                    return Err(MoveError::Blocked(square));
+                }
+                if self
+                    .occupant(square)
+                    .is_some_and(|other| other != id && is_hostile(other))
+                {
+                    return Err(MoveError::Occupied(square));
+                }
+            }
+
+            // Each diagonal costs what it adds to the distance, so a path measures the same as
+            // `distance` under every rule.
+            let diagonal = current.x != step.x && current.y != step.y;
+            let mut step_cost = SQUARE_FEET;
+            if diagonal {
+                diagonals += 1;
+                step_cost = squares_to_feet(diagonals, diagonals, self.diagonal_rule)
+                    - squares_to_feet(diagonals - 1, diagonals - 1, self.diagonal_rule);
+            }
+            if footprint(*step, size).iter().any(|square| {
+                self.terrain_at(*square) == Terrain::Difficult
+                    || self.occupant(*square).is_some_and(|other| other != id)
+            }) {
+                step_cost += SQUARE_FEET;
+            }
+
+            costs.push(step_cost);
+            current = *step;
+        }
+
+        let cost = costs.iter().sum();
+        if cost > remaining {
+            return Err(MoveError::InsufficientMovement {
+                required: cost,
+                remaining,
+            });
+        }
+        if let Some(square) = footprint(current, size)
+            .into_iter()
+            .find(|square| self.occupant(*square).is_some_and(|other| other != id))
+        {
+            return Err(MoveError::Occupied(square));
+        }
+
+        Ok(costs)
+    }
+
+    // Moves a creature along `path`, spending its movement. Returns the feet of movement spent.
+    pub fn move_creature(
+        &mut self,
+        id: u32,
+        path: &[Position],
+        is_hostile: impl Fn(u32) -> bool,
+    ) -> Result<u32, MoveError> {
+        let cost = self.path_costs(id, path, is_hostile)?.iter().sum();
+        if let Some(destination) = path.last() {
+            self.step(id, *destination, cost);
+        }
+
+        Ok(cost)
+    }
+
+    // Puts a creature on `position` and spends `cost` feet of its movement without any
+    // validation; check the path with `path_costs` first.
+    pub fn step(&mut self, id: u32, position: Position, cost: u32) {
+        if let Some(placement) = self.placements.get_mut(&id) {
+            placement.position = position;
+            placement.movement_remaining = placement.movement_remaining.saturating_sub(cost);
+        }
+    }
+
+    pub fn distance_between_squares(&self, from: Position, to: Position) -> u32 {
+        squares_to_feet(
+            (from.x - to.x).unsigned_abs(),
+            (from.y - to.y).unsigned_abs(),
+            self.diagonal_rule,
+        )
+    }
+
+    // Distance between the closest squares occupied by two creatures, so adjacent creatures
+    // are always 5 feet apart regardless of size.
+    pub fn distance(&self, a: u32, b: u32) -> Option<u32> {
+        let a = self.placements.get(&a)?;
+        self.distance_from(a.position, &a.size, b)
+    }
+
+    // Distance to creature `other` from a creature of `size` standing at `position`.
+    pub fn distance_from(&self, position: Position, size: &Size, other: u32) -> Option<u32> {
+        let other = self.placements.get(&other)?;
+
+        let gap = |a_start: i32, a_len: i32, b_start: i32, b_len: i32| {
+            (b_start - (a_start + a_len - 1))
+                .max(a_start - (b_start + b_len - 1))
+                .max(0) as u32
+        };
+        let (a_len, b_len) = (size.squares() as i32, other.size.squares() as i32);
+        let dx = gap(position.x, a_len, other.position.x, b_len);
+        let dy = gap(position.y, a_len, other.position.y, b_len);
+
+        Some(squares_to_feet(dx, dy, self.diagonal_rule))
+    }
+
+    pub fn distance_to_point(&self, id: u32, x: f32, y: f32) -> Option<f32> {
+        let placement = self.placements.get(&id)?;
+        let half = placement.size.squares() as f32 / 2.0;
+        let center_x = placement.position.x as f32 + half;
+        let center_y = placement.position.y as f32 + half;
+        let dx = (center_x - x).abs();
+        let dy = (center_y - y).abs();
+
+        Some((dx * dx + dy * dy).sqrt() * SQUARE_FEET as f32)
+    }
+
+    pub fn within_reach(&self, attacker: u32, target: u32, reach: u32) -> Option<bool> {
+        self.distance(attacker, target)
+            .map(|distance| distance <= reach)
+    }
+
+    pub fn check_range(
+        &self,
+        attacker: u32,
+        target: u32,
+        range: AttackRange,
+    ) -> Option<RangeCheck> {
+        let distance = self.distance(attacker, target)?;
+        let check = match range {
+            AttackRange::Melee { reach } if distance <= reach => RangeCheck::InRange,
+            AttackRange::Melee { .. } => RangeCheck::OutOfRange,
+            AttackRange::Ranged { normal, .. } if distance <= normal => RangeCheck::InRange,
+            AttackRange::Ranged { long, .. } if distance <= long => RangeCheck::LongRange,
+            AttackRange::Ranged { .. } => RangeCheck::OutOfRange,
+        };
+
+        Some(check)
+    }
+
+    // Uses the grid variant of the cover rules: from the attacker corner that gives the best
+    // view, trace lines to the corners of a target square and count how many walls block.
+    // Creatures in the way grant half cover at most.
+    pub fn cover(&self, attacker: u32, target: u32) -> Option<Cover> {
+        let attacker = self.placements.get(&attacker)?;
+        let target = self.placements.get(&target)?;
+
+        let mut fewest_blocked = 4;
+        for origin in footprint(attacker.position, &attacker.size)
+            .into_iter()
+            .flat_map(corners)
+        {
+            for square in footprint(target.position, &target.size) {
+                let blocked = corners(square)
+                    .into_iter()
+                    .filter(|corner| self.line_blocked_by_walls(origin, *corner))
+                    .count();
+                fewest_blocked = fewest_blocked.min(blocked);
+            }
+        }
+
+        let wall_cover = match fewest_blocked {
+            0 => Cover::None,
+            1 | 2 => Cover::Half,
+            3 => Cover::ThreeQuarters,
+            _ => Cover::Total,
+        };
+        let creature_cover = if self.line_blocked_by_creatures(attacker, target) {
+            Cover::Half
+        } else {
+            Cover::None
+        };
+
+        Some(wall_cover.max(creature_cover))
+    }
+
+    pub fn has_line_of_sight(&self, observer: u32, target: u32) -> Option<bool> {
+        self.cover(observer, target)
+            .map(|cover| cover != Cover::Total)
+    }
+
+    fn line_blocked_by_walls(&self, from: (f32, f32), to: (f32, f32)) -> bool {
+        squares_touched(from, to).into_iter().any(|squares| {
+            squares
+                .iter()
+                .all(|square| self.in_bounds(*square) && self.terrain_at(*square) == Terrain::Wall)
+        })
+    }
+
+    fn line_blocked_by_creatures(&self, from: &Placement, to: &Placement) -> bool {
+        let own_squares = [
+            footprint(from.position, &from.size),
+            footprint(to.position, &to.size),
+        ]
+        .concat();
+
+        squares_touched(center(from), center(to))
+            .into_iter()
+            .filter(|squares| squares.len() == 1 && !own_squares.contains(&squares[0]))
+            .any(|squares| self.occupant(squares[0]).is_some())
+    }
+}
+
+pub fn footprint(position: Position, size: &Size) -> Vec<Position> {
+    let squares = size.squares() as i32;
+    let mut occupied = vec![];
+    for dx in 0..squares {
+        for dy in 0..squares {
+            occupied.push(Position::new(position.x + dx, position.y + dy));
+        }
+    }
+
+    occupied
+}
+
+fn corners(square: Position) -> [(f32, f32); 4] {
+    let (x, y) = (square.x as f32, square.y as f32);
+    [(x, y), (x + 1.0, y), (x, y + 1.0), (x + 1.0, y + 1.0)]
+}
+
+fn center(placement: &Placement) -> (f32, f32) {
+    let half = placement.size.squares() as f32 / 2.0;
+    (
+        placement.position.x as f32 + half,
+        placement.position.y as f32 + half,
+    )
+}
+
+// For each point sampled along the segment, the squares that point touches: one inside a
+// square, two on an edge and four on a corner. A line running along an edge is only stopped
+// when the squares on both sides of it block.
+fn squares_touched(from: (f32, f32), to: (f32, f32)) -> Vec<Vec<Position>> {
+    const SAMPLES_PER_SQUARE: f32 = 20.0;
+    const EDGE: f32 = 0.01;
+
+    let touched_columns = |value: f32| {
+        let nearest = value.round();
+        if (value - nearest).abs() < EDGE {
+            vec![nearest as i32 - 1, nearest as i32]
+        } else {
+            vec![value.floor() as i32]
+        }
+    };
+
+    let length = ((to.0 - from.0).powi(2) + (to.1 - from.1).powi(2)).sqrt();
+    let samples = (length * SAMPLES_PER_SQUARE).ceil() as u32;
+    let mut touched = vec![];
+    for i in 1..samples {
+        let t = i as f32 / samples as f32;
+        let xs = touched_columns(from.0 + (to.0 - from.0) * t);
+        let ys = touched_columns(from.1 + (to.1 - from.1) * t);
+        touched.push(
+            xs.iter()
+                .flat_map(|x| ys.iter().map(|y| Position::new(*x, *y)))
+                .collect(),
+        );
+    }
+
+    touched
+}
+
+// JSON object keys have to be strings, so square-keyed maps are stored as a list of pairs.
+mod square_entries {
+    use std::collections::HashMap;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::Position;
+
+    pub fn serialize<T, S>(map: &HashMap<Position, T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Serialize,
+        S: Serializer,
+    {
+        map.iter().collect::<Vec<_>>().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<HashMap<Position, T>, D::Error>
+    where
+        T: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        let entries = Vec::<(Position, T)>::deserialize(deserializer)?;
+        Ok(entries.into_iter().collect())
+    }
+}
+
+fn squares_to_feet(dx: u32, dy: u32, rule: DiagonalRule) -> u32 {
+    let diagonal = dx.min(dy);
+    let straight = dx.max(dy) - diagonal;
+    match rule {
+        DiagonalRule::Standard => dx.max(dy) * SQUARE_FEET,
+        DiagonalRule::Alternating => (straight + diagonal + diagonal / 2) * SQUARE_FEET,
+        DiagonalRule::Euclidean => {
+            let squares = ((dx * dx + dy * dy) as f32).sqrt();
+            (squares * SQUARE_FEET as f32).round() as u32
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distance_with_diagonal_rules() {
+        let mut map = BattleMap::new(20, 20);
+        map.place(1, Position::new(0, 0), Size::Medium).unwrap();
+        map.place(2, Position::new(3, 3), Size::Medium).unwrap();
+        map.place(3, Position::new(5, 0), Size::Large).unwrap();
+
+        assert_eq!(map.distance(1, 2), Some(15));
+        assert_eq!(map.distance(1, 3), Some(25));
+        assert_eq!(map.distance(2, 3), Some(10));
+
+        map.diagonal_rule = DiagonalRule::Alternating;
+        assert_eq!(map.distance(1, 2), Some(20));
+
+        assert_eq!(
+            map.check_range(1, 2, AttackRange::Melee { reach: 5 }),
+            Some(RangeCheck::OutOfRange)
+        );
+        assert_eq!(
+            map.check_range(
+                1,
+                2,
+                AttackRange::Ranged {
+                    normal: 15,
+                    long: 60
+                }
+            ),
+            Some(RangeCheck::LongRange)
+        );
+    }
+
+    #[test]
+    fn test_move_creature_spends_movement() {
+        let mut map = BattleMap::new(10, 10);
+        map.place(1, Position::new(0, 0), Size::Medium).unwrap();
+        map.place(2, Position::new(3, 0), Size::Medium).unwrap();
+        map.set_terrain(Position::new(1, 1), Terrain::Difficult);
+        map.start_turn(1, 30);
+
+        let path = [Position::new(1, 1), Position::new(2, 2)];
+        assert_eq!(map.move_creature(1, &path, |_| true), Ok(15));
+        assert_eq!(map.placement(1).unwrap().movement_remaining, 15);
+
+        assert_eq!(
+            map.move_creature(1, &[Position::new(3, 1), Position::new(3, 0)], |_| true),
+            Err(MoveError::Occupied(Position::new(3, 0)))
+        );
+        assert_eq!(
+            map.move_creature(1, &[Position::new(2, 5)], |_| true),
+            Err(MoveError::NotAdjacent(Position::new(2, 5)))
+        );
+        assert_eq!(map.position_of(1), Some(Position::new(2, 2)));
+
+        map.set_terrain(Position::new(2, 3), Terrain::Wall);
+        assert_eq!(
+            map.move_creature(1, &[Position::new(2, 3)], |_| true),
+            Err(MoveError::Blocked(Position::new(2, 3)))
+        );
+
+        let serialized = serde_json::to_string(&map).unwrap();
+        let deserialized: BattleMap = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(map, deserialized);
+    }
+
+    #[test]
+    fn test_moving_past_other_creatures() {
+        let mut map = BattleMap::new(10, 10);
+        map.place(1, Position::new(0, 0), Size::Medium).unwrap();
+        map.place(2, Position::new(1, 0), Size::Medium).unwrap();
+        map.start_turn(1, 30);
+
+        let path = [Position::new(1, 0), Position::new(2, 0)];
+        assert_eq!(
+            map.path_costs(1, &path, |_| true),
+            Err(MoveError::Occupied(Position::new(1, 0)))
+        );
+        // An ally can be squeezed past like difficult terrain, but not stopped on.
+        assert_eq!(map.path_costs(1, &path, |_| false), Ok(vec![10, 5]));
+        assert_eq!(
+            map.path_costs(1, &path[..1], |_| false),
+            Err(MoveError::Occupied(Position::new(1, 0)))
+        );
+
+        map.remove(2);
+        map.diagonal_rule = DiagonalRule::Euclidean;
+        map.start_turn(1, 60);
+        let path: Vec<Position> = (1..=6).map(|i| Position::new(i, i)).collect();
+        let cost = map.move_creature(1, &path, |_| true).unwrap();
+        assert_eq!(cost, 42);
+        assert_eq!(
+            map.distance_between_squares(Position::new(0, 0), Position::new(6, 6)),
+            cost
+        );
+    }
+
+    #[test]
+    fn test_cover_from_walls_and_creatures() {
+        let mut map = BattleMap::new(10, 10);
+        map.place(1, Position::new(0, 2), Size::Medium).unwrap();
+        map.place(2, Position::new(4, 2), Size::Medium).unwrap();
+        assert_eq!(map.cover(1, 2), Some(Cover::None));
+
+        map.place(3, Position::new(2, 2), Size::Medium).unwrap();
+        assert_eq!(map.cover(1, 2), Some(Cover::Half));
+        map.remove(3);
+
+        map.set_terrain(Position::new(3, 2), Terrain::Wall);
+        assert_eq!(map.cover(1, 2), Some(Cover::Half));
+
+        map.set_terrain(Position::new(3, 1), Terrain::Wall);
+        map.set_terrain(Position::new(3, 3), Terrain::Wall);
+        assert_eq!(map.cover(1, 2), Some(Cover::Total));
+        assert_eq!(map.has_line_of_sight(1, 2), Some(false));
+
+        // The edge of the map isn't a wall, so a line along it is only blocked like one
+        // running past open ground.
+        let mut map = BattleMap::new(10, 10);
+        for y in [0, 5] {
+            map.place(y as u32 + 1, Position::new(0, y), Size::Medium)
+                .unwrap();
+            map.place(y as u32 + 2, Position::new(4, y), Size::Medium)
+                .unwrap();
+            map.set_terrain(Position::new(2, y), Terrain::Wall);
+            map.set_terrain(Position::new(2, y + 1), Terrain::Wall);
+        }
+        assert_eq!(map.cover(6, 7), Some(Cover::Half));
+        assert_eq!(map.cover(1, 2), Some(Cover::Half));
+    }
+}
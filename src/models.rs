@@ -1,59 +1,1019 @@
-use rand::Rng;
+use std::{cell::RefCell, collections::HashMap};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
 
-use crate::effect::Effect;
+use crate::{
+    builder::MAX_ABILITY_SCORE,
+    classes::{
+        class_definition, full_caster_slots, CasterProgression, ClassDefinition, ClassFeature,
+        FeatureEffect, FeatureError, FightingStyle, MulticlassError, SpellSlotError, Spellcasting,
+        Subclass,
+    },
+    currency::{CurrencyError, Purse},
+    effect::{
+        Buff, BuffKind, ConditionKind, DamageKind, Duration, DurationUnit, Effect, EnhancementBuff,
+    },
+    feats::{feat, FeatChoices, FeatEffect, FeatError, FeatPrerequisite},
+    items::{
+        Armor, ArmorCategory, ArmorClassFormula, EquipError, Equipment, EquipmentSlot, Equippable,
+        SpellScroll, Weapon, WeaponCategory, WeaponError, WeaponMode, WeaponProficiency,
+        WeaponProperty, WondrousItem,
+    },
+    leveling::{Advancement, MAX_LEVEL},
+    races::{race_definition, Trait},
+    resources::{Resource, ResourceError, SPELL_SLOT_COSTS, UNLIMITED},
+    rest::RestError,
+};
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub struct Character {
     pub id: u32,
     pub name: String,
     pub race: Race,
-    pub class: ClassDetails,
-    pub level: u32,
+    pub subrace: Option<String>,
+    pub background: Option<String>,
+    // Classes in the order they were taken; the first one is the starting class.
+    pub classes: Vec<ClassDetails>,
+    pub advancement: Advancement,
+    // Feats taken instead of ability score improvements.
+    pub feats: Vec<String>,
+    // Spell slots of each level used since they were last regained.
+    pub expended_spell_slots: [u32; 9],
+    // Uses of each class resource pool spent since it was last recovered.
+    pub resources_spent: HashMap<String, u32>,
+    // Bardic Inspiration die the character can add to one roll.
+    pub inspiration: Option<Dice>,
     pub abilities: Abilities,
     pub skills: Skills,
     pub proficiencies: SkillProficiencies,
+    pub weapon_proficiencies: Vec<WeaponProficiency>,
+    pub armor_proficiencies: Vec<ArmorCategory>,
+    // Tools and vehicles by name, e.g. "Thieves' Tools".
+    pub tool_proficiencies: Vec<String>,
+    pub saving_throw_proficiencies: Vec<SavingThrow>,
     pub inventory: Inventory,
+    pub equipment: Equipment,
     pub hit_points: HitPoints,
     pub armor_class: i32,
+    pub speed: u32,
+    // Optional rule where carrying more than 5x Strength in pounds slows the character down.
+    pub variant_encumbrance: bool,
+    // Darkvision range in feet.
+    pub darkvision: u32,
+    pub languages: Vec<String>,
+    pub resistances: Vec<DamageKind>,
+    pub traits: Vec<Trait>,
     pub active_effects: Vec<Effect>,
 }
 
 impl Character {
-    pub fn ability_check(&self, ability: Ability) -> i32 {
-        rand::thread_rng().gen_range(1..=20) + ability.get_modifier()
+    pub fn has_condition(&self, kind: ConditionKind) -> bool {
+        self.active_effects.iter().any(|effect| match effect {
+            Effect::Condition(condition) => condition.kind == kind,
+            _ => false,
+        })
     }
 
-    pub fn skill_check(&self, skill: Skill) -> i32 {
-        let proficiency = match skill.kind {
-            SkillType::Acrobatics => &self.proficiencies.acrobatics,
-            SkillType::AnimalHandling => &self.proficiencies.animal_handling,
-            SkillType::Arcana => &self.proficiencies.arcana,
-            SkillType::Athletics => &self.proficiencies.athletics,
-            SkillType::Deception => &self.proficiencies.deception,
-            SkillType::History => &self.proficiencies.history,
-            SkillType::Insight => &self.proficiencies.insight,
-            SkillType::Intimidation => &self.proficiencies.intimidation,
-            SkillType::Investigation => &self.proficiencies.investigation,
-            SkillType::Medicine => &self.proficiencies.medicine,
-            SkillType::Nature => &self.proficiencies.nature,
-            SkillType::Perception => &self.proficiencies.perception,
-            SkillType::Performance => &self.proficiencies.performance,
-            SkillType::Persuasion => &self.proficiencies.persuasion,
-            SkillType::Religion => &self.proficiencies.religion,
-            SkillType::SleightOfHand => &self.proficiencies.sleight_of_hand,
-            SkillType::Stealth => &self.proficiencies.stealth,
-            SkillType::Survival => &self.proficiencies.survival,
+    pub fn has_trait(&self, name: &str) -> bool {
+        self.traits.iter().any(|t| t.name == name)
+    }
+
+    // Halflings reroll a natural 1 and must use the new roll. With advantage or disadvantage
+    // each die is rerolled before one is picked.
+    pub fn roll_d20(&self, mode: RollMode) -> RollResult {
+        let threshold = if self.has_trait("Lucky") { 1 } else { 0 };
+        Dice::roll_d20_rerolling(mode, threshold)
+    }
+
+    // Total character level across all classes.
+    pub fn level(&self) -> u32 {
+        self.classes.iter().map(|class| class.level).sum()
+    }
+
+    pub fn class_level(&self, class: &Class) -> u32 {
+        self.class_details(class).map_or(0, |details| details.level)
+    }
+
+    pub fn class_details(&self, class: &Class) -> Option<&ClassDetails> {
+        self.classes.iter().find(|details| details.kind == *class)
+    }
+
+    fn class_details_mut(&mut self, class: &Class) -> Result<&mut ClassDetails, FeatureError> {
+        self.classes
+            .iter_mut()
+            .find(|details| details.kind == *class)
+            .ok_or_else(|| FeatureError::NoClassLevels(class.clone()))
+    }
+
+    // Class and subclass features gained up to the level of each class.
+    pub fn class_features(&self) -> Vec<&'static ClassFeature> {
+        self.classes
+            .iter()
+            .flat_map(|class| class.features())
+            .collect()
+    }
+
+    pub fn feature_effects(&self) -> Vec<&'static FeatureEffect> {
+        self.class_features()
+            .into_iter()
+            .flat_map(|feature| &feature.effects)
+            .collect()
+    }
+
+    // Number of attacks made with the Attack action. Extra Attack from several classes
+    // doesn't stack.
+    pub fn attacks_per_action(&self) -> u32 {
+        self.feature_effects()
+            .iter()
+            .filter_map(|effect| match effect {
+                FeatureEffect::ExtraAttack(attacks) => Some(*attacks),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(1)
+    }
+
+    pub fn sneak_attack_dice(&self) -> Option<Dice> {
+        self.feature_effects()
+            .iter()
+            .filter_map(|effect| match effect {
+                FeatureEffect::SneakAttack(dice) => Some(dice.clone()),
+                _ => None,
+            })
+            .max_by_key(|dice| dice.count)
+    }
+
+    pub fn has_fighting_style(&self, style: FightingStyle) -> bool {
+        self.classes
+            .iter()
+            .any(|class| class.fighting_styles.contains(&style))
+    }
+
+    // The subclass can only be picked once, at or after the class level the class lists.
+    pub fn choose_subclass(&mut self, class: &Class, name: &str) -> Result<(), FeatureError> {
+        let details = self.class_details_mut(class)?;
+        let definition = details.definition();
+        if details.subclass.is_some() {
+            return Err(FeatureError::SubclassAlreadyChosen);
+        }
+        if definition.subclass(name).is_none() {
+            return Err(FeatureError::UnknownSubclass(name.to_string()));
+        }
+        if details.level < definition.subclass_level {
+            return Err(FeatureError::SubclassTooEarly {
+                level: definition.subclass_level,
+            });
+        }
+
+        details.subclass = Some(name.to_string());
+        self.update_armor_class();
+        Ok(())
+    }
+
+    // Each Fighting Style feature of a class allows one style not already known.
+    pub fn choose_fighting_style(
+        &mut self,
+        class: &Class,
+        style: FightingStyle,
+    ) -> Result<(), FeatureError> {
+        if self.has_fighting_style(style) {
+            return Err(FeatureError::DuplicateFightingStyle(style));
+        }
+        let details = self.class_details_mut(class)?;
+        if details.fighting_styles.len() >= details.fighting_style_choices() {
+            return Err(FeatureError::NoFightingStyleAvailable);
+        }
+
+        details.fighting_styles.push(style);
+        self.update_armor_class();
+        Ok(())
+    }
+
+    // A single spellcasting class uses its own table. Several combine their caster levels on
+    // the full caster table, with pact magic slots kept on top.
+    pub fn spell_slots(&self) -> [u32; 9] {
+        let casters: Vec<(&Spellcasting, u32)> = self
+            .classes
+            .iter()
+            .filter_map(|class| Some((class.spellcasting()?, class.level)))
+            .collect();
+        let (pact, others): (Vec<_>, Vec<_>) = casters
+            .into_iter()
+            .partition(|(spellcasting, _)| spellcasting.progression == CasterProgression::Pact);
+
+        let mut slots = match others[..] {
+            [] => [0; 9],
+            [(spellcasting, level)] => spellcasting.progression.spell_slots(level),
+            _ => full_caster_slots(
+                others
+                    .iter()
+                    .map(|(spellcasting, level)| {
+                        spellcasting.progression.multiclass_caster_level(*level)
+                    })
+                    .sum(),
+            ),
         };
+        for (spellcasting, level) in pact {
+            let pact_slots = spellcasting.progression.spell_slots(level);
+            for (slot, pact_slot) in slots.iter_mut().zip(pact_slots) {
+                *slot += pact_slot;
+            }
+        }
+
+        slots
+    }
+
+    // Spellcasting ability modifier plus proficiency. Multiclass casters use their best
+    // spellcasting ability, and characters without one fall back on Intelligence.
+    pub fn spell_attack_bonus(&self) -> i32 {
+        let modifier = self
+            .classes
+            .iter()
+            .filter_map(|class| class.spellcasting())
+            .map(|spellcasting| self.abilities.get(&spellcasting.ability).get_modifier())
+            .max()
+            .unwrap_or(self.abilities.intelligence.get_modifier());
+
+        modifier + self.proficiency_bonus()
+    }
+
+    // Experience only counts towards levels for characters that don't level by milestone.
+    pub fn gain_experience(&mut self, experience: u32) {
+        if let Advancement::Experience(total) = &mut self.advancement {
+            *total += experience;
+        }
+    }
+
+    pub fn reach_milestone(&mut self) {
+        if let Advancement::Milestone { level } = &mut self.advancement {
+            *level = (*level + 1).min(MAX_LEVEL);
+        }
+    }
+
+    pub fn proficiency_bonus(&self) -> i32 {
+        2 + (self.level().max(1) as i32 - 1) / 4
+    }
+
+    // One pool of hit dice per class, e.g. 3d10 and 2d6 for a fighter 3 / sorcerer 2.
+    pub fn hit_dice(&self) -> Vec<Dice> {
+        self.classes
+            .iter()
+            .map(|class| Dice {
+                count: class.level as i32,
+                sides: class.hit_dice as i32,
+            })
+            .collect()
+    }
+
+    // Hit dice left to spend, by class.
+    pub fn remaining_hit_dice(&self) -> Vec<Dice> {
+        self.classes
+            .iter()
+            .map(|class| Dice {
+                count: (class.level - class.hit_dice_spent) as i32,
+                sides: class.hit_dice as i32,
+            })
+            .collect()
+    }
+
+    // Spends one of the class's hit dice, healing its roll plus the Constitution modifier.
+    // Returns the hit points regained.
+    pub fn spend_hit_die(&mut self, class: &Class) -> Result<i32, RestError> {
+        let bonus =
+            self.abilities.constitution.get_modifier() + self.modifier(EnhancementBuff::HitDice);
+        let details = self
+            .classes
+            .iter_mut()
+            .find(|details| details.kind == *class && details.hit_dice_spent < details.level)
+            .ok_or_else(|| RestError::NoHitDice(class.clone()))?;
+        details.hit_dice_spent += 1;
+        let roll = Dice {
+            count: 1,
+            sides: details.hit_dice as i32,
+        }
+        .roll()
+        .total;
+
+        Ok(self.heal((roll + bonus).max(0)))
+    }
+
+    pub fn remaining_spell_slots(&self) -> [u32; 9] {
+        let mut slots = self.spell_slots();
+        for (slot, expended) in slots.iter_mut().zip(self.expended_spell_slots) {
+            *slot = slot.saturating_sub(expended);
+        }
+        slots
+    }
+
+    pub fn expend_spell_slot(&mut self, level: u32) -> Result<(), SpellSlotError> {
+        if !(1..=9).contains(&level) {
+            return Err(SpellSlotError::InvalidLevel(level));
+        }
+        let index = level as usize - 1;
+        if self.remaining_spell_slots()[index] == 0 {
+            return Err(SpellSlotError::NoSlotsLeft(level));
+        }
+        self.expended_spell_slots[index] += 1;
+        Ok(())
+    }
+
+    // Resource pools granted by the character's class features, with the level of the class
+    // that grants each.
+    pub fn resources(&self) -> Vec<(&'static Resource, u32)> {
+        self.classes
+            .iter()
+            .flat_map(|class| {
+                class
+                    .features()
+                    .flat_map(|feature| &feature.effects)
+                    .filter_map(move |effect| match effect {
+                        FeatureEffect::Resource(resource) => Some((resource, class.level)),
+                        _ => None,
+                    })
+            })
+            .collect()
+    }
+
+    // A pool granted by several classes, like Channel Divinity, doesn't gain extra uses.
+    pub fn resource_maximum(&self, name: &str) -> Option<u32> {
+        self.resources()
+            .into_iter()
+            .filter(|(resource, _)| resource.name == name)
+            .map(|(resource, level)| resource.maximum(level, &self.abilities))
+            .max()
+    }
+
+    pub fn resource_remaining(&self, name: &str) -> Option<u32> {
+        let spent = self.resources_spent.get(name).copied().unwrap_or(0);
+        Some(self.resource_maximum(name)?.saturating_sub(spent))
+    }
+
+    // Spends uses from the pool, returning how many are left. Unlimited pools are never spent.
+    pub fn spend_resource(&mut self, name: &str, amount: u32) -> Result<u32, ResourceError> {
+        let remaining = self
+            .resource_remaining(name)
+            .ok_or_else(|| ResourceError::UnknownResource(name.to_string()))?;
+        if self.resource_maximum(name) == Some(UNLIMITED) {
+            return Ok(UNLIMITED);
+        }
+        if amount > remaining {
+            return Err(ResourceError::NotEnough { remaining });
+        }
+
+        *self.resources_spent.entry(name.to_string()).or_insert(0) += amount;
+        Ok(remaining - amount)
+    }
+
+    // Restores uses up to the pool's maximum, returning how many were restored.
+    pub fn restore_resource(&mut self, name: &str, amount: u32) -> Result<u32, ResourceError> {
+        if self.resource_maximum(name).is_none() {
+            return Err(ResourceError::UnknownResource(name.to_string()));
+        }
+
+        let spent = self.resources_spent.entry(name.to_string()).or_insert(0);
+        let restored = amount.min(*spent);
+        *spent -= restored;
+        Ok(restored)
+    }
+
+    // Font of Magic: spends sorcery points to regain an expended spell slot of up to 5th level.
+    pub fn create_spell_slot(&mut self, level: u32) -> Result<(), ResourceError> {
+        let cost = *level
+            .checked_sub(1)
+            .and_then(|index| SPELL_SLOT_COSTS.get(index as usize))
+            .ok_or(ResourceError::InvalidSpellSlot(level))?;
+        if self.expended_spell_slots[level as usize - 1] == 0 {
+            return Err(ResourceError::NoExpendedSlot(level));
+        }
+
+        self.spend_resource("Sorcery Points", cost)?;
+        self.expended_spell_slots[level as usize - 1] -= 1;
+        Ok(())
+    }
+
+    // Font of Magic: expends a spell slot to regain as many sorcery points as its level.
+    pub fn convert_spell_slot(&mut self, level: u32) -> Result<(), ResourceError> {
+        if self.resource_maximum("Sorcery Points").is_none() {
+            return Err(ResourceError::UnknownResource(String::from(
+                "Sorcery Points",
+            )));
+        }
+        self.expend_spell_slot(level).map_err(|error| match error {
+            SpellSlotError::InvalidLevel(level) => ResourceError::InvalidSpellSlot(level),
+            SpellSlotError::NoSlotsLeft(_) => ResourceError::NotEnough { remaining: 0 },
+        })?;
+
+        self.restore_resource("Sorcery Points", level)?;
+        Ok(())
+    }
+
+    // Extra melee damage while raging, for barbarians.
+    pub fn rage_damage(&self) -> Option<i32> {
+        self.feature_effects()
+            .iter()
+            .filter_map(|effect| match effect {
+                FeatureEffect::RageDamage(damage) => Some(*damage),
+                _ => None,
+            })
+            .max()
+    }
+
+    // Die the character hands out with Bardic Inspiration.
+    pub fn inspiration_die(&self) -> Option<Dice> {
+        self.feature_effects()
+            .iter()
+            .filter_map(|effect| match effect {
+                FeatureEffect::InspirationDie(sides) => Some(*sides),
+                _ => None,
+            })
+            .max()
+            .map(|sides| Dice { count: 1, sides })
+    }
+
+    // Rolls the Bardic Inspiration die, if the character has one, to add to a roll.
+    pub fn use_inspiration(&mut self) -> Option<i32> {
+        Some(self.inspiration.take()?.roll().total)
+    }
+
+    // Warlocks regain their pact magic slots on a short rest, as do the resource pools that
+    // recover on one.
+    pub fn finish_short_rest(&mut self) {
+        for class in &self.classes {
+            let Some(spellcasting) = class.spellcasting() else {
+                continue;
+            };
+            if spellcasting.progression != CasterProgression::Pact {
+                continue;
+            }
+            let pact_slots = spellcasting.progression.spell_slots(class.level);
+            for (expended, pact_slot) in self.expended_spell_slots.iter_mut().zip(pact_slots) {
+                *expended = expended.saturating_sub(pact_slot);
+            }
+        }
+
+        for (resource, level) in self.resources() {
+            if resource.recovers_on_short_rest(level) {
+                self.resources_spent.remove(&resource.name);
+            }
+        }
+    }
+
+    // Restores hit points, spell slots and resource pools, regains up to half the character's
+    // hit dice (at least one), largest first, and removes a level of exhaustion. Characters at
+    // 0 hit points don't benefit.
+    pub fn finish_long_rest(&mut self) {
+        if self.hit_points.current == 0 {
+            return;
+        }
+
+        self.hit_points.current = self.hit_points.max;
+        self.hit_points.temporary = 0;
+        self.expended_spell_slots = [0; 9];
+        self.resources_spent.clear();
+
+        let mut regained = (self.level() / 2).max(1);
+        let mut classes: Vec<&mut ClassDetails> = self.classes.iter_mut().collect();
+        classes.sort_by_key(|class| std::cmp::Reverse(class.hit_dice));
+        for class in classes {
+            let amount = class.hit_dice_spent.min(regained);
+            class.hit_dice_spent -= amount;
+            regained -= amount;
+        }
+
+        if let Some(index) = self.active_effects.iter().position(|effect| {
+            matches!(effect, Effect::Condition(condition) if condition.kind == ConditionKind::Exhaustion)
+        }) {
+            self.active_effects.remove(index);
+        }
+    }
+
+    // Takes a level in a class: another level in a class the character has, or a first level
+    // in a new one. A new class needs the prerequisites of every class involved and only
+    // grants its multiclassing proficiencies, with skills picked from its list. Hit points
+    // and other choices are handled by `LevelUp`.
+    pub fn add_class_level(
+        &mut self,
+        class: &Class,
+        skills: &[SkillType],
+    ) -> Result<(), MulticlassError> {
+        if self.level() >= 20 {
+            return Err(MulticlassError::MaxLevel);
+        }
+
+        if let Some(details) = self
+            .classes
+            .iter_mut()
+            .find(|details| details.kind == *class)
+        {
+            details.level += 1;
+            return Ok(());
+        }
+
+        let definition = class_definition(class);
+        for details in &self.classes {
+            if !details
+                .definition()
+                .multiclassing
+                .meets_prerequisites(&self.abilities)
+            {
+                return Err(MulticlassError::PrerequisiteNotMet(details.kind.clone()));
+            }
+        }
+        let multiclassing = &definition.multiclassing;
+        if !multiclassing.meets_prerequisites(&self.abilities) {
+            return Err(MulticlassError::PrerequisiteNotMet(class.clone()));
+        }
+        let skills_are_valid = skills.len() == multiclassing.skill_choices
+            && skills.iter().enumerate().all(|(i, skill)| {
+                definition.skill_options.contains(skill) && !skills[..i].contains(skill)
+            });
+        if !skills_are_valid {
+            return Err(MulticlassError::InvalidSkillChoices);
+        }
+
+        for skill in skills {
+            if *self.proficiencies.get(skill) == Proficiency::NotProficient {
+                self.proficiencies.set(skill, Proficiency::Proficient);
+            }
+        }
+        for armor in &multiclassing.armor_proficiencies {
+            if !self.armor_proficiencies.contains(armor) {
+                self.armor_proficiencies.push(*armor);
+            }
+        }
+        for weapon in &multiclassing.weapon_proficiencies {
+            if !self.weapon_proficiencies.contains(weapon) {
+                self.weapon_proficiencies.push(weapon.clone());
+            }
+        }
+        for tool in &multiclassing.tool_proficiencies {
+            if !self.tool_proficiencies.contains(tool) {
+                self.tool_proficiencies.push(tool.clone());
+            }
+        }
+
+        self.classes.push(ClassDetails::new(class));
+        self.update_armor_class();
+        Ok(())
+    }
+
+    pub fn is_proficient_with(&self, weapon: &Weapon) -> bool {
+        self.weapon_proficiencies
+            .iter()
+            .any(|proficiency| match proficiency {
+                WeaponProficiency::Simple => weapon.category == WeaponCategory::Simple,
+                WeaponProficiency::Martial => weapon.category == WeaponCategory::Martial,
+                WeaponProficiency::Weapon(name) => *name == weapon.name,
+            })
+    }
+
+    // Active effects together with the effects of equipped (and, where needed, attuned) items
+    // and the buffs of feats.
+    pub fn effects(&self) -> Vec<Effect> {
+        let mut effects = self.active_effects.clone();
+        effects.extend(self.equipment.effects());
+        for effect in self.feat_effects() {
+            if let FeatEffect::Buff(kind) = effect {
+                effects.push(Effect::Buff(Buff {
+                    kind: kind.clone(),
+                    duration: Duration {
+                        amount: 0,
+                        unit: DurationUnit::Permanent,
+                    },
+                }));
+            }
+        }
+        effects
+    }
+
+    pub fn feat_effects(&self) -> Vec<&'static FeatEffect> {
+        self.feats
+            .iter()
+            .filter_map(|name| feat(name))
+            .flat_map(|feat| &feat.effects)
+            .collect()
+    }
+
+    pub fn meets_prerequisite(&self, prerequisite: &FeatPrerequisite) -> bool {
+        match prerequisite {
+            FeatPrerequisite::Ability(ability, score) => {
+                self.abilities.get(ability).value >= *score
+            }
+            FeatPrerequisite::ArmorProficiency(armor) => self.armor_proficiencies.contains(armor),
+            FeatPrerequisite::Race(races) => races.contains(&self.race),
+        }
+    }
+
+    // Takes a feat from the catalog, applying its one-off benefits. Buffs, power attacks and
+    // reactions stay registered through the feat itself.
+    pub fn take_feat(&mut self, name: &str, choices: &FeatChoices) -> Result<(), FeatError> {
+        let feat = feat(name).ok_or_else(|| FeatError::UnknownFeat(name.to_string()))?;
+        if self.feats.iter().any(|taken| taken == name) {
+            return Err(FeatError::AlreadyTaken(name.to_string()));
+        }
+        if let Some(prerequisite) = feat
+            .prerequisites
+            .iter()
+            .find(|prerequisite| !self.meets_prerequisite(prerequisite))
+        {
+            return Err(FeatError::PrerequisiteNotMet(prerequisite.clone()));
+        }
+
+        let mut character = self.clone();
+        let constitution = character.abilities.constitution.get_modifier();
+        for effect in &feat.effects {
+            match effect {
+                FeatEffect::AbilityIncrease(ability, amount) => {
+                    character.increase_ability(ability, *amount)?;
+                }
+                FeatEffect::AbilityChoice(options) => match &choices.ability {
+                    Some(ability) if options.contains(ability) => {
+                        character.increase_ability(ability, 1)?;
+                    }
+                    _ => return Err(FeatError::InvalidAbilityChoice),
+                },
+                FeatEffect::SavingThrowChoice => match &choices.ability {
+                    Some(ability) => {
+                        if !character.saving_throw_proficiencies.contains(ability) {
+                            character.saving_throw_proficiencies.push(ability.clone());
+                        }
+                    }
+                    None => return Err(FeatError::InvalidAbilityChoice),
+                },
+                FeatEffect::ArmorProficiency(armor) => {
+                    if !character.armor_proficiencies.contains(armor) {
+                        character.armor_proficiencies.push(*armor);
+                    }
+                }
+                FeatEffect::WeaponProficiency(weapon) => {
+                    if !character.weapon_proficiencies.contains(weapon) {
+                        character.weapon_proficiencies.push(weapon.clone());
+                    }
+                }
+                FeatEffect::SkillChoices(count) => {
+                    let skills = &choices.skills;
+                    let skills_are_valid = skills.len() == *count
+                        && skills.iter().enumerate().all(|(i, skill)| {
+                            *character.proficiencies.get(skill) == Proficiency::NotProficient
+                                && !skills[..i].contains(skill)
+                        });
+                    if !skills_are_valid {
+                        return Err(FeatError::InvalidSkillChoices);
+                    }
+                    for skill in skills {
+                        character.proficiencies.set(skill, Proficiency::Proficient);
+                    }
+                }
+                FeatEffect::HitPointsPerLevel(amount) => {
+                    character.hit_points.max += amount * character.level() as i32;
+                    character.hit_points.current += amount * character.level() as i32;
+                }
+                FeatEffect::Buff(_) | FeatEffect::PowerAttack(_) | FeatEffect::Reaction(_) => {}
+            }
+        }
+
+        // A higher Constitution modifier raises the hit points of every level.
+        let hit_points = (character.abilities.constitution.get_modifier() - constitution)
+            * character.level() as i32;
+        character.hit_points.max += hit_points;
+        character.hit_points.current += hit_points;
+
+        character.feats.push(name.to_string());
+        character.skills = Skills::from_abilities(&character.abilities);
+        character.update_armor_class();
+        *self = character;
+        Ok(())
+    }
+
+    fn increase_ability(&mut self, ability: &SavingThrow, amount: u32) -> Result<(), FeatError> {
+        let score = self.abilities.get_mut(ability);
+        if score.value + amount > MAX_ABILITY_SCORE {
+            return Err(FeatError::AbilityTooHigh(ability.clone()));
+        }
+        score.value += amount;
+        Ok(())
+    }
+
+    pub fn can_power_attack(&self, weapon: &Weapon) -> bool {
+        self.is_proficient_with(weapon)
+            && self.feat_effects().iter().any(|effect| match effect {
+                FeatEffect::PowerAttack(power_attack) => power_attack.allows(weapon),
+                _ => false,
+            })
+    }
+
+    pub fn is_resistant_to(&self, kind: &DamageKind) -> bool {
+        self.resistances.contains(kind)
+            || self.effects().iter().any(|effect| {
+                matches!(effect, Effect::Buff(Buff { kind: BuffKind::Resistance(resisted), .. }) if resisted == kind)
+            })
+    }
+
+    pub fn roll_initiative(&self) -> i32 {
+        self.roll_d20(RollMode::Normal).total
+            + self.abilities.dexterity.get_modifier()
+            + self.modifier(EnhancementBuff::Initiative)
+    }
+
+    // Total bonus to a statistic from buffs. Plain enhancements are worth +2.
+    pub fn modifier(&self, stat: EnhancementBuff) -> i32 {
+        self.effects()
+            .iter()
+            .map(|effect| match effect {
+                Effect::Buff(buff) => match &buff.kind {
+                    BuffKind::Enhancement(enhanced) if *enhanced == stat => 2,
+                    BuffKind::Bonus(enhanced, amount) if *enhanced == stat => *amount,
+                    _ => 0,
+                },
+                _ => 0,
+            })
+            .sum()
+    }
+
+    pub fn armor_class_formulas(&self) -> Vec<ArmorClassFormula> {
+        let mut formulas = vec![ArmorClassFormula::Unarmored];
+        for effect in self.feature_effects() {
+            if let FeatureEffect::ArmorClassFormula(formula) = effect {
+                formulas.push(formula.clone());
+            }
+        }
+        for effect in self.effects() {
+            if let Effect::Buff(buff) = effect {
+                if let BuffKind::ArmorClassFormula(formula) = buff.kind {
+                    formulas.push(formula);
+                }
+            }
+        }
+
+        formulas
+    }
+
+    pub fn calculate_armor_class(&self) -> i32 {
+        let dexterity = self.abilities.dexterity.get_modifier();
+        let shield = self.equipment.shield.as_ref();
+
+        let base = match &self.equipment.armor {
+            Some(armor) => armor.armor_class(dexterity),
+            None => self
+                .armor_class_formulas()
+                .iter()
+                .filter_map(|formula| {
+                    formula.armor_class(
+                        dexterity,
+                        self.abilities.constitution.get_modifier(),
+                        self.abilities.wisdom.get_modifier(),
+                        shield.is_some(),
+                    )
+                })
+                .max()
+                .unwrap_or(10 + dexterity),
+        };
+        let shield_bonus = shield.map_or(0, |shield| shield.armor_class + shield.magic_bonus);
+        let style_bonus = match &self.equipment.armor {
+            Some(_) if self.has_fighting_style(FightingStyle::Defense) => 1,
+            _ => 0,
+        };
+
+        base + shield_bonus + style_bonus + self.modifier(EnhancementBuff::ArmorClass)
+    }
+
+    pub fn update_armor_class(&mut self) {
+        self.armor_class = self.calculate_armor_class();
+    }
+
+    // Equips an item in a slot, returning whatever was there before. Armor and shields need
+    // the matching armor proficiency.
+    pub fn equip(
+        &mut self,
+        item: Equippable,
+        slot: EquipmentSlot,
+    ) -> Result<Option<Equippable>, EquipError> {
+        if let Equippable::Armor(armor) = &item {
+            if !self.armor_proficiencies.contains(&armor.category) {
+                return Err(EquipError::NotProficient(armor.name.clone()));
+            }
+        }
+
+        let previous = self.equipment.equip(item, slot)?;
+        self.update_armor_class();
+
+        Ok(previous)
+    }
+
+    // Equips an item carried in the inventory. The item stays in the inventory, which holds
+    // everything the character carries, worn or not.
+    pub fn equip_item(
+        &mut self,
+        name: &str,
+        slot: EquipmentSlot,
+    ) -> Result<Option<Equippable>, EquipError> {
+        let item = self
+            .inventory
+            .get(name)
+            .ok_or_else(|| EquipError::NotCarried(name.to_string()))?;
+        let equippable = item.as_equippable().ok_or(EquipError::WrongSlot(slot))?;
+
+        self.equip(equippable, slot)
+    }
+
+    pub fn unequip(&mut self, slot: EquipmentSlot) -> Option<Equippable> {
+        let removed = self.equipment.unequip(slot);
+        self.update_armor_class();
+
+        removed
+    }
+
+    // Takes the ammunition or thrown weapon an attack uses out of the inventory. Attacks that
+    // use neither return `Ok(None)`.
+    pub fn draw_ammunition(
+        &mut self,
+        weapon: &Weapon,
+        mode: WeaponMode,
+    ) -> Result<Option<Item>, WeaponError> {
+        let name = match (&weapon.ammunition, mode) {
+            (_, WeaponMode::Thrown) => &weapon.name,
+            (Some(ammunition), _) if weapon.has_property(&WeaponProperty::Ammunition) => ammunition,
+            _ => return Ok(None),
+        };
+
+        let item = self
+            .inventory
+            .remove(name, 1)
+            .ok_or_else(|| WeaponError::OutOfAmmunition(name.clone()))?;
+
+        // The last thrown weapon of its kind leaves the hand holding it.
+        if mode == WeaponMode::Thrown && self.inventory.quantity_of(name) == 0 {
+            for slot in [EquipmentSlot::MainHand, EquipmentSlot::OffHand] {
+                if self
+                    .equipment
+                    .get(slot)
+                    .is_some_and(|held| held.name() == name.as_str())
+                {
+                    self.unequip(slot);
+                }
+            }
+        }
+
+        Ok(Some(item))
+    }
+
+    pub fn attune(&mut self, name: &str) -> Result<(), EquipError> {
+        self.equipment.attune(name)?;
+        self.update_armor_class();
+
+        Ok(())
+    }
+
+    pub fn end_attunement(&mut self, name: &str) -> bool {
+        let ended = self.equipment.end_attunement(name);
+        self.update_armor_class();
+
+        ended
+    }
+
+    pub fn size(&self) -> Size {
+        self.race.size()
+    }
+
+    // Weight carried in pounds, including coins at 50 to the pound.
+    pub fn carried_weight(&self) -> f32 {
+        self.inventory.total_weight() + self.inventory.purse.weight()
+    }
+
+    pub fn carrying_capacity(&self) -> f32 {
+        self.abilities.strength.value as f32 * 15.0 * self.size().carrying_multiplier()
+    }
+
+    pub fn push_drag_lift(&self) -> f32 {
+        self.carrying_capacity() * 2.0
+    }
+
+    pub fn encumbrance(&self) -> Encumbrance {
+        if !self.variant_encumbrance {
+            return Encumbrance::Unencumbered;
+        }
+
+        let strength = self.abilities.strength.value as f32 * self.size().carrying_multiplier();
+        let carried = self.carried_weight();
+        if carried > strength * 10.0 {
+            Encumbrance::HeavilyEncumbered
+        } else if carried > strength * 5.0 {
+            Encumbrance::Encumbered
+        } else {
+            Encumbrance::Unencumbered
+        }
+    }
+
+    // Heavily encumbered characters have disadvantage on checks, saves and attacks that use
+    // Strength, Dexterity or Constitution.
+    fn encumbrance_disadvantage(&self, ability: &SavingThrow) -> bool {
+        self.encumbrance() == Encumbrance::HeavilyEncumbered
+            && matches!(
+                ability,
+                SavingThrow::Strength | SavingThrow::Dexterity | SavingThrow::Constitution
+            )
+    }
+
+    // Heals up to the character's maximum hit points, returning the amount actually healed.
+    pub fn heal(&mut self, amount: i32) -> i32 {
+        let healed = amount.clamp(0, self.hit_points.max - self.hit_points.current);
+        self.hit_points.current += healed;
+        healed
+    }
+
+    // Heavy armor slows a wearer who doesn't meet its Strength requirement by 10 feet.
+    pub fn current_speed(&self) -> u32 {
+        let too_heavy = self.equipment.armor.as_ref().is_some_and(|armor| {
+            armor
+                .strength_requirement
+                .is_some_and(|strength| self.abilities.strength.value < strength)
+        });
+
+        let carried = self.carried_weight();
+        if carried > self.push_drag_lift() {
+            return 0;
+        }
+
+        let mut speed = self.speed as i32 + self.modifier(EnhancementBuff::Speed);
+        if too_heavy {
+            speed -= 10;
+        }
+        speed -= match self.encumbrance() {
+            Encumbrance::Unencumbered => 0,
+            Encumbrance::Encumbered => 10,
+            Encumbrance::HeavilyEncumbered => 20,
+        };
+
+        // Anything beyond carrying capacity can only be pushed or dragged.
+        if carried > self.carrying_capacity() {
+            speed = speed.min(5);
+        }
+        speed.max(0) as u32
+    }
+
+    pub fn attack_roll_mode(&self) -> RollMode {
+        let disadvantage = self.encumbrance_disadvantage(&SavingThrow::Strength);
+        RollMode::resolve(false, disadvantage)
+    }
+
+    pub fn saving_throw_mode(&self, saving_throw: &SavingThrow) -> RollMode {
+        RollMode::resolve(false, self.encumbrance_disadvantage(saving_throw))
+    }
+
+    pub fn skill_check_mode(&self, skill: &SkillType) -> RollMode {
+        let stealth_disadvantage = *skill == SkillType::Stealth
+            && self
+                .equipment
+                .armor
+                .as_ref()
+                .is_some_and(|armor| armor.stealth_disadvantage);
+
+        RollMode::resolve(
+            false,
+            stealth_disadvantage || self.encumbrance_disadvantage(&skill.ability()),
+        )
+    }
+
+    pub fn ability_check_mode(&self, ability: &SavingThrow) -> RollMode {
+        RollMode::resolve(false, self.encumbrance_disadvantage(ability))
+    }
+
+    pub fn ability_check(&self, ability: &SavingThrow) -> i32 {
+        self.roll_d20(self.ability_check_mode(ability)).total
+            + self.abilities.get(ability).get_modifier()
+    }
+
+    pub fn skill_check(&self, skill: Skill) -> i32 {
+        let proficiency = self.proficiencies.get(&skill.kind);
 
         let proficiency_bonus = match proficiency {
             Proficiency::NotProficient => 0,
-            Proficiency::Expertise => 2 * self.level as i32,
-            Proficiency::Proficient => self.level as i32,
-            Proficiency::HalfProficient => (self.level as f32 / 2.0).floor() as i32,
+            Proficiency::Expertise => 2 * self.proficiency_bonus(),
+            Proficiency::Proficient => self.proficiency_bonus(),
+            Proficiency::HalfProficient => self.proficiency_bonus() / 2,
+        };
+
+        self.roll_d20(self.skill_check_mode(&skill.kind)).total
+            + skill.value as i32
+            + proficiency_bonus
+    }
+
+    // Check with a tool, adding the proficiency bonus when proficient with it, e.g. Dexterity
+    // with thieves' tools to pick a lock.
+    pub fn tool_check(&self, tool: &str, ability: &SavingThrow) -> i32 {
+        let proficiency_bonus = if self.tool_proficiencies.iter().any(|t| t == tool) {
+            self.proficiency_bonus()
+        } else {
+            0
         };
 
-        rand::thread_rng().gen_range(1..=20) + skill.value as i32 + proficiency_bonus
+        let mode = RollMode::resolve(false, self.encumbrance_disadvantage(ability));
+        self.roll_d20(mode).total + self.abilities.get(ability).get_modifier() + proficiency_bonus
+    }
+
+    pub fn knows_language(&self, language: &str) -> bool {
+        self.languages.iter().any(|known| known == language)
     }
 
     pub fn saving_throw(&self, saving_throw: SavingThrow) -> i32 {
@@ -66,11 +1026,11 @@ impl Character {
             SavingThrow::Charisma => &self.abilities.charisma,
         };
 
-        if self.class.saving_throws.contains(&saving_throw) {
-            // TODO: Add proficiency bonus
-            ability.get_modifier() + self.level as i32
+        let bonus = self.modifier(EnhancementBuff::SavingThrow);
+        if self.saving_throw_proficiencies.contains(&saving_throw) {
+            ability.get_modifier() + self.proficiency_bonus() + bonus
         } else {
-            ability.get_modifier()
+            ability.get_modifier() + bonus
         }
     }
 }
@@ -86,30 +1046,106 @@ pub struct RollResult {
     pub rolls: Vec<i32>,
 }
 
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy)]
+pub enum RollMode {
+    Normal,
+    Advantage,
+    Disadvantage,
+}
+
+impl RollMode {
+    // Any number of advantage sources and disadvantage sources cancel out to a normal roll.
+    pub fn resolve(advantage: bool, disadvantage: bool) -> Self {
+        match (advantage, disadvantage) {
+            (true, false) => RollMode::Advantage,
+            (false, true) => RollMode::Disadvantage,
+            _ => RollMode::Normal,
+        }
+    }
+}
+
+thread_local! {
+    // Every die is rolled from here so that seeding it makes a session repeatable.
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+pub fn seed_rng(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
+fn roll_die(sides: i32) -> i32 {
+    RNG.with(|rng| rng.borrow_mut().gen_range(1..=sides))
+}
+
 impl Dice {
     pub fn roll(&self) -> RollResult {
-        let mut rng = rand::thread_rng();
         let mut rolls = vec![];
         let mut total = 0;
 
         for _ in 0..self.count {
-            let roll = rng.gen_range(1..=self.sides);
+            let roll = roll_die(self.sides);
             total += roll;
             rolls.push(roll);
         }
 
         RollResult { total, rolls }
     }
+
+    // Rolls again, once, any die showing `threshold` or lower and keeps the new roll.
+    pub fn roll_rerolling(&self, threshold: i32) -> RollResult {
+        let single = Dice {
+            count: 1,
+            sides: self.sides,
+        };
+        let rolls: Vec<i32> = self
+            .roll()
+            .rolls
+            .into_iter()
+            .map(|roll| {
+                if roll <= threshold {
+                    single.roll().total
+                } else {
+                    roll
+                }
+            })
+            .collect();
+
+        RollResult {
+            total: rolls.iter().sum(),
+            rolls,
+        }
+    }
+
+    pub fn roll_d20(mode: RollMode) -> RollResult {
+        Dice::roll_d20_rerolling(mode, 0)
+    }
+
+    // Rerolls any d20 showing `threshold` or lower before advantage or disadvantage picks one.
+    pub fn roll_d20_rerolling(mode: RollMode, threshold: i32) -> RollResult {
+        let d20s = Dice {
+            count: if mode == RollMode::Normal { 1 } else { 2 },
+            sides: 20,
+        };
+        let rolls = d20s.roll_rerolling(threshold).rolls;
+
+        let total = match mode {
+            RollMode::Normal => rolls[0],
+            RollMode::Advantage => *rolls.iter().max().unwrap(),
+            RollMode::Disadvantage => *rolls.iter().min().unwrap(),
+        };
+
+        RollResult { total, rolls }
+    }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub struct HitPoints {
     pub current: i32,
     pub max: i32,
     pub temporary: i32,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub struct Skills {
     pub acrobatics: Skill,
     pub animal_handling: Skill,
@@ -131,7 +1167,7 @@ pub struct Skills {
     pub survival: Skill,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub enum Proficiency {
     NotProficient,
     Expertise,
@@ -139,7 +1175,7 @@ pub enum Proficiency {
     HalfProficient,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub struct SkillProficiencies {
     pub acrobatics: Proficiency,
     pub animal_handling: Proficiency,
@@ -161,7 +1197,81 @@ pub struct SkillProficiencies {
     pub survival: Proficiency,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+impl Default for SkillProficiencies {
+    fn default() -> Self {
+        Self {
+            acrobatics: Proficiency::NotProficient,
+            animal_handling: Proficiency::NotProficient,
+            arcana: Proficiency::NotProficient,
+            athletics: Proficiency::NotProficient,
+            deception: Proficiency::NotProficient,
+            history: Proficiency::NotProficient,
+            insight: Proficiency::NotProficient,
+            intimidation: Proficiency::NotProficient,
+            investigation: Proficiency::NotProficient,
+            medicine: Proficiency::NotProficient,
+            nature: Proficiency::NotProficient,
+            perception: Proficiency::NotProficient,
+            performance: Proficiency::NotProficient,
+            persuasion: Proficiency::NotProficient,
+            religion: Proficiency::NotProficient,
+            sleight_of_hand: Proficiency::NotProficient,
+            stealth: Proficiency::NotProficient,
+            survival: Proficiency::NotProficient,
+        }
+    }
+}
+
+impl SkillProficiencies {
+    pub fn get(&self, skill: &SkillType) -> &Proficiency {
+        match skill {
+            SkillType::Acrobatics => &self.acrobatics,
+            SkillType::AnimalHandling => &self.animal_handling,
+            SkillType::Arcana => &self.arcana,
+            SkillType::Athletics => &self.athletics,
+            SkillType::Deception => &self.deception,
+            SkillType::History => &self.history,
+            SkillType::Insight => &self.insight,
+            SkillType::Intimidation => &self.intimidation,
+            SkillType::Investigation => &self.investigation,
+            SkillType::Medicine => &self.medicine,
+            SkillType::Nature => &self.nature,
+            SkillType::Perception => &self.perception,
+            SkillType::Performance => &self.performance,
+            SkillType::Persuasion => &self.persuasion,
+            SkillType::Religion => &self.religion,
+            SkillType::SleightOfHand => &self.sleight_of_hand,
+            SkillType::Stealth => &self.stealth,
+            SkillType::Survival => &self.survival,
+        }
+    }
+
+    pub fn set(&mut self, skill: &SkillType, proficiency: Proficiency) {
+        let current = match skill {
+            SkillType::Acrobatics => &mut self.acrobatics,
+            SkillType::AnimalHandling => &mut self.animal_handling,
+            SkillType::Arcana => &mut self.arcana,
+            SkillType::Athletics => &mut self.athletics,
+            SkillType::Deception => &mut self.deception,
+            SkillType::History => &mut self.history,
+            SkillType::Insight => &mut self.insight,
+            SkillType::Intimidation => &mut self.intimidation,
+            SkillType::Investigation => &mut self.investigation,
+            SkillType::Medicine => &mut self.medicine,
+            SkillType::Nature => &mut self.nature,
+            SkillType::Perception => &mut self.perception,
+            SkillType::Performance => &mut self.performance,
+            SkillType::Persuasion => &mut self.persuasion,
+            SkillType::Religion => &mut self.religion,
+            SkillType::SleightOfHand => &mut self.sleight_of_hand,
+            SkillType::Stealth => &mut self.stealth,
+            SkillType::Survival => &mut self.survival,
+        };
+        *current = proficiency;
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub enum SavingThrow {
     Strength,
     Dexterity,
@@ -171,7 +1281,57 @@ pub enum SavingThrow {
     Charisma,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+impl SavingThrow {
+    pub const ALL: [SavingThrow; 6] = [
+        SavingThrow::Strength,
+        SavingThrow::Dexterity,
+        SavingThrow::Constitution,
+        SavingThrow::Intelligence,
+        SavingThrow::Wisdom,
+        SavingThrow::Charisma,
+    ];
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub enum Size {
+    Tiny,
+    Small,
+    Medium,
+    Large,
+    Huge,
+    Gargantuan,
+}
+
+impl Size {
+    // Number of 5-foot squares a creature of this size controls along each side.
+    pub fn squares(&self) -> u32 {
+        match self {
+            Size::Tiny | Size::Small | Size::Medium => 1,
+            Size::Large => 2,
+            Size::Huge => 3,
+            Size::Gargantuan => 4,
+        }
+    }
+
+    pub fn carrying_multiplier(&self) -> f32 {
+        match self {
+            Size::Tiny => 0.5,
+            Size::Small | Size::Medium => 1.0,
+            Size::Large => 2.0,
+            Size::Huge => 4.0,
+            Size::Gargantuan => 8.0,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy)]
+pub enum Encumbrance {
+    Unencumbered,
+    Encumbered,
+    HeavilyEncumbered,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub enum Race {
     Human,
     Elf,
@@ -183,7 +1343,13 @@ pub enum Race {
     Tiefling,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+impl Race {
+    pub fn size(&self) -> Size {
+        race_definition(self).size.clone()
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub enum Class {
     Fighter,
     Wizard,
@@ -199,88 +1365,747 @@ pub enum Class {
     Warlock,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub struct ClassDetails {
+    pub kind: Class,
     pub name: String,
+    pub level: u32,
     pub hit_dice: u32,
+    // Hit dice spent since they were last regained.
+    pub hit_dice_spent: u32,
     pub saving_throws: Vec<SavingThrow>,
+    pub subclass: Option<String>,
+    pub fighting_styles: Vec<FightingStyle>,
+    pub spells_known: Vec<String>,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+impl ClassDetails {
+    pub fn new(class: &Class) -> Self {
+        let definition = class_definition(class);
+        Self {
+            kind: class.clone(),
+            name: format!("{:?}", class),
+            level: 1,
+            hit_dice: definition.hit_die,
+            hit_dice_spent: 0,
+            saving_throws: definition.saving_throws.clone(),
+            subclass: None,
+            fighting_styles: vec![],
+            spells_known: vec![],
+        }
+    }
+
+    pub fn definition(&self) -> &'static ClassDefinition {
+        class_definition(&self.kind)
+    }
+
+    pub fn subclass(&self) -> Option<&'static Subclass> {
+        self.subclass
+            .as_ref()
+            .and_then(|name| self.definition().subclass(name))
+    }
+
+    // Class and subclass features gained up to the class level.
+    pub fn features(&self) -> impl Iterator<Item = &'static ClassFeature> + '_ {
+        let subclass_features = self
+            .subclass()
+            .map_or(&[][..], |subclass| &subclass.features[..]);
+        self.definition()
+            .features
+            .iter()
+            .chain(subclass_features)
+            .filter(|feature| feature.level <= self.level)
+    }
+
+    pub fn fighting_style_choices(&self) -> usize {
+        self.features()
+            .flat_map(|feature| &feature.effects)
+            .filter(|effect| **effect == FeatureEffect::FightingStyle)
+            .count()
+    }
+
+    // Spellcasting of the class, or of its subclass for e.g. an Eldritch Knight.
+    pub fn spellcasting(&self) -> Option<&'static Spellcasting> {
+        self.definition().spellcasting.as_ref().or(self
+            .subclass()
+            .and_then(|subclass| subclass.spellcasting.as_ref()))
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub struct Ability {
     pub value: u32,
 }
 
-#[derive(Debug, PartialEq, Clone)]
-pub enum SkillType {
-    Acrobatics,
-    AnimalHandling,
-    Arcana,
-    Athletics,
-    Deception,
-    History,
-    Insight,
-    Intimidation,
-    Investigation,
-    Medicine,
-    Nature,
-    Perception,
-    Performance,
-    Persuasion,
-    Religion,
-    SleightOfHand,
-    Stealth,
-    Survival,
-}
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub enum SkillType {
+    Acrobatics,
+    AnimalHandling,
+    Arcana,
+    Athletics,
+    Deception,
+    History,
+    Insight,
+    Intimidation,
+    Investigation,
+    Medicine,
+    Nature,
+    Perception,
+    Performance,
+    Persuasion,
+    Religion,
+    SleightOfHand,
+    Stealth,
+    Survival,
+}
+
+impl SkillType {
+    pub const ALL: [SkillType; 18] = [
+        SkillType::Acrobatics,
+        SkillType::AnimalHandling,
+        SkillType::Arcana,
+        SkillType::Athletics,
+        SkillType::Deception,
+        SkillType::History,
+        SkillType::Insight,
+        SkillType::Intimidation,
+        SkillType::Investigation,
+        SkillType::Medicine,
+        SkillType::Nature,
+        SkillType::Perception,
+        SkillType::Performance,
+        SkillType::Persuasion,
+        SkillType::Religion,
+        SkillType::SleightOfHand,
+        SkillType::Stealth,
+        SkillType::Survival,
+    ];
+
+    // The ability a skill is based on.
+    pub fn ability(&self) -> SavingThrow {
+        match self {
+            SkillType::Athletics => SavingThrow::Strength,
+            SkillType::Acrobatics | SkillType::SleightOfHand | SkillType::Stealth => {
+                SavingThrow::Dexterity
+            }
+            SkillType::Arcana
+            | SkillType::History
+            | SkillType::Investigation
+            | SkillType::Nature
+            | SkillType::Religion => SavingThrow::Intelligence,
+            SkillType::AnimalHandling
+            | SkillType::Insight
+            | SkillType::Medicine
+            | SkillType::Perception
+            | SkillType::Survival => SavingThrow::Wisdom,
+            SkillType::Deception
+            | SkillType::Intimidation
+            | SkillType::Performance
+            | SkillType::Persuasion => SavingThrow::Charisma,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Skill {
+    pub kind: SkillType,
+    pub value: u32,
+}
+
+impl Ability {
+    pub fn get_modifier(&self) -> i32 {
+        (self.value as i32 - 10).div_euclid(2)
+    }
+}
+
+impl Skills {
+    // Skill values hold the modifier of the ability each skill is based on. Skill values
+    // can't go negative, so penalties are dropped.
+    pub fn from_abilities(abilities: &Abilities) -> Self {
+        let value = |skill: SkillType| abilities.get(&skill.ability()).get_modifier().max(0) as u32;
+
+        Self {
+            acrobatics: Skill {
+                kind: SkillType::Acrobatics,
+                value: value(SkillType::Acrobatics),
+            },
+            animal_handling: Skill {
+                kind: SkillType::AnimalHandling,
+                value: value(SkillType::AnimalHandling),
+            },
+            arcana: Skill {
+                kind: SkillType::Arcana,
+                value: value(SkillType::Arcana),
+            },
+            athletics: Skill {
+                kind: SkillType::Athletics,
+                value: value(SkillType::Athletics),
+            },
+            deception: Skill {
+                kind: SkillType::Deception,
+                value: value(SkillType::Deception),
+            },
+            history: Skill {
+                kind: SkillType::History,
+                value: value(SkillType::History),
+            },
+            insight: Skill {
+                kind: SkillType::Insight,
+                value: value(SkillType::Insight),
+            },
+            intimidation: Skill {
+                kind: SkillType::Intimidation,
+                value: value(SkillType::Intimidation),
+            },
+            investigation: Skill {
+                kind: SkillType::Investigation,
+                value: value(SkillType::Investigation),
+            },
+            medicine: Skill {
+                kind: SkillType::Medicine,
+                value: value(SkillType::Medicine),
+            },
+            nature: Skill {
+                kind: SkillType::Nature,
+                value: value(SkillType::Nature),
+            },
+            perception: Skill {
+                kind: SkillType::Perception,
+                value: value(SkillType::Perception),
+            },
+            performance: Skill {
+                kind: SkillType::Performance,
+                value: value(SkillType::Performance),
+            },
+            persuasion: Skill {
+                kind: SkillType::Persuasion,
+                value: value(SkillType::Persuasion),
+            },
+            religion: Skill {
+                kind: SkillType::Religion,
+                value: value(SkillType::Religion),
+            },
+            sleight_of_hand: Skill {
+                kind: SkillType::SleightOfHand,
+                value: value(SkillType::SleightOfHand),
+            },
+            stealth: Skill {
+                kind: SkillType::Stealth,
+                value: value(SkillType::Stealth),
+            },
+            survival: Skill {
+                kind: SkillType::Survival,
+                value: value(SkillType::Survival),
+            },
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Abilities {
+    pub strength: Ability,
+    pub dexterity: Ability,
+    pub constitution: Ability,
+    pub intelligence: Ability,
+    pub wisdom: Ability,
+    pub charisma: Ability,
+}
+
+impl Abilities {
+    pub fn get(&self, ability: &SavingThrow) -> &Ability {
+        match ability {
+            SavingThrow::Strength => &self.strength,
+            SavingThrow::Dexterity => &self.dexterity,
+            SavingThrow::Constitution => &self.constitution,
+            SavingThrow::Intelligence => &self.intelligence,
+            SavingThrow::Wisdom => &self.wisdom,
+            SavingThrow::Charisma => &self.charisma,
+        }
+    }
+
+    pub fn get_mut(&mut self, ability: &SavingThrow) -> &mut Ability {
+        match ability {
+            SavingThrow::Strength => &mut self.strength,
+            SavingThrow::Dexterity => &mut self.dexterity,
+            SavingThrow::Constitution => &mut self.constitution,
+            SavingThrow::Intelligence => &mut self.intelligence,
+            SavingThrow::Wisdom => &mut self.wisdom,
+            SavingThrow::Charisma => &mut self.charisma,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Inventory {
+    pub items: Vec<Item>,
+    pub purse: Purse,
+}
+
+impl Inventory {
+    // Adds an item, merging it into an existing stack when possible.
+    pub fn add(&mut self, item: Item) {
+        match self
+            .items
+            .iter_mut()
+            .find(|existing| existing.can_stack_with(&item))
+        {
+            Some(stack) => stack.quantity += item.quantity,
+            None => self.items.push(item),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Item> {
+        self.items.iter().find(|item| item.name == name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Item> {
+        self.items.iter_mut().find(|item| item.name == name)
+    }
+
+    pub fn quantity_of(&self, name: &str) -> u32 {
+        self.items
+            .iter()
+            .filter(|item| item.name == name)
+            .map(|item| item.quantity)
+            .sum()
+    }
+
+    // Takes `quantity` units out of the first stack with the given name, dropping the stack
+    // once it is empty.
+    pub fn remove(&mut self, name: &str, quantity: u32) -> Option<Item> {
+        let index = self.items.iter().position(|item| item.name == name)?;
+        if self.items[index].quantity <= quantity {
+            return Some(self.items.remove(index));
+        }
+
+        self.items[index].split(quantity)
+    }
+
+    // Splits part of a stack off into a separate stack of its own.
+    pub fn split(&mut self, name: &str, quantity: u32) -> Option<&Item> {
+        let split = self.get_mut(name)?.split(quantity)?;
+        self.items.push(split);
+        self.items.last()
+    }
+
+    // Spends a charge or, for items without charges, one unit of the stack. Returns a copy of
+    // the item that was used.
+    pub fn consume(&mut self, name: &str) -> Option<Item> {
+        let item = self.get_mut(name)?;
+        match &mut item.charges {
+            Some(charges) if charges.current == 0 => None,
+            Some(charges) => {
+                charges.current -= 1;
+                Some(item.clone())
+            }
+            None => self.remove(name, 1),
+        }
+    }
+
+    pub fn recharge_at_dawn(&mut self) {
+        for item in self.items.iter_mut() {
+            if let Some(charges) = &mut item.charges {
+                charges.recharge();
+            }
+        }
+    }
+
+    pub fn total_weight(&self) -> f32 {
+        self.items.iter().map(|item| item.total_weight()).sum()
+    }
+
+    // Total value in copper pieces.
+    pub fn total_value(&self) -> u32 {
+        self.items.iter().map(|item| item.total_value()).sum()
+    }
+
+    // Pays for an item out of the purse and adds it to the inventory.
+    pub fn buy(&mut self, item: Item) -> Result<(), CurrencyError> {
+        self.purse
+            .pay(item.total_value(), &format!("Bought {}", item.name))?;
+        self.add(item);
+        Ok(())
+    }
+
+    pub fn items_in(&self, category: &ItemCategory) -> Vec<&Item> {
+        self.items
+            .iter()
+            .filter(|item| {
+                std::mem::discriminant(&item.category) == std::mem::discriminant(category)
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub enum ItemCategory {
+    Weapon(Weapon),
+    Armor(Armor),
+    Wondrous(WondrousItem),
+    Scroll(SpellScroll),
+    Gear,
+    Consumable,
+    Tool,
+    Treasure,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy)]
+pub enum Rarity {
+    Mundane,
+    Common,
+    Uncommon,
+    Rare,
+    VeryRare,
+    Legendary,
+    Artifact,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Charges {
+    pub current: u32,
+    pub maximum: u32,
+    // Charges regained at dawn. Items without a roll regain all of them.
+    pub recharge: Option<Dice>,
+}
+
+impl Charges {
+    pub fn recharge(&mut self) -> u32 {
+        let missing = self.maximum - self.current;
+        let regained = match &self.recharge {
+            Some(dice) => (dice.roll().total.max(0) as u32).min(missing),
+            None => missing,
+        };
+
+        self.current += regained;
+        regained
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Item {
+    pub name: String,
+    pub category: ItemCategory,
+    pub quantity: u32,
+    // Weight of a single unit in pounds.
+    pub weight: f32,
+    // Cost of a single unit in copper pieces.
+    pub cost: u32,
+    pub rarity: Rarity,
+    pub description: String,
+    pub stackable: bool,
+    pub charges: Option<Charges>,
+    pub properties: HashMap<String, String>,
+    // Applied to the target when the item is used, e.g. a potion's healing.
+    pub effects: Vec<Effect>,
+}
+
+impl Item {
+    pub fn new(name: &str, category: ItemCategory) -> Self {
+        let stackable = matches!(
+            category,
+            ItemCategory::Gear
+                | ItemCategory::Consumable
+                | ItemCategory::Scroll(_)
+                | ItemCategory::Treasure
+        );
+
+        Self {
+            name: name.to_string(),
+            category,
+            quantity: 1,
+            weight: 0.0,
+            cost: 0,
+            rarity: Rarity::Mundane,
+            description: String::new(),
+            stackable,
+            charges: None,
+            properties: HashMap::new(),
+            effects: Vec::new(),
+        }
+    }
+
+    pub fn with_quantity(mut self, quantity: u32) -> Self {
+        self.quantity = quantity;
+        self
+    }
+
+    pub fn with_weight(mut self, weight: f32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn with_cost(mut self, cost: u32) -> Self {
+        self.cost = cost;
+        self
+    }
+
+    pub fn with_rarity(mut self, rarity: Rarity) -> Self {
+        self.rarity = rarity;
+        self
+    }
+
+    pub fn with_description(mut self, description: &str) -> Self {
+        self.description = description.to_string();
+        self
+    }
+
+    pub fn with_stackable(mut self, stackable: bool) -> Self {
+        self.stackable = stackable;
+        self
+    }
+
+    pub fn with_charges(mut self, maximum: u32) -> Self {
+        self.charges = Some(Charges {
+            current: maximum,
+            maximum,
+            recharge: None,
+        });
+        self
+    }
+
+    pub fn with_recharge(mut self, dice: Dice) -> Self {
+        if let Some(charges) = &mut self.charges {
+            charges.recharge = Some(dice);
+        }
+        self
+    }
+
+    pub fn with_effects(mut self, effects: Vec<Effect>) -> Self {
+        self.effects = effects;
+        self
+    }
+
+    pub fn with_property(mut self, key: &str, value: &str) -> Self {
+        self.properties.insert(key.to_string(), value.to_string());
+        self
+    }
 
-#[derive(Debug, PartialEq, Clone)]
-pub struct Skill {
-    pub kind: SkillType,
-    pub value: u32,
-}
+    // Items with charges are tracked individually since each one can be in a different state.
+    pub fn can_stack_with(&self, other: &Item) -> bool {
+        self.stackable
+            && other.stackable
+            && self.charges.is_none()
+            && other.charges.is_none()
+            && self.name == other.name
+            && self.category == other.category
+            && self.properties == other.properties
+    }
 
-impl Ability {
-    pub fn get_modifier(&self) -> i32 {
-        (self.value as i32 - 10) / 2
+    pub fn split(&mut self, quantity: u32) -> Option<Item> {
+        if !self.stackable || quantity == 0 || quantity >= self.quantity {
+            return None;
+        }
+
+        self.quantity -= quantity;
+        Some(Item {
+            quantity,
+            ..self.clone()
+        })
     }
-}
 
-#[derive(Debug, PartialEq, Clone)]
-pub struct Abilities {
-    pub strength: Ability,
-    pub dexterity: Ability,
-    pub constitution: Ability,
-    pub intelligence: Ability,
-    pub wisdom: Ability,
-    pub charisma: Ability,
-}
+    pub fn total_weight(&self) -> f32 {
+        self.weight * self.quantity as f32
+    }
 
-#[derive(Debug, PartialEq, Clone)]
-pub struct Inventory {
-    pub items: Vec<Item>,
-}
+    pub fn total_value(&self) -> u32 {
+        self.cost * self.quantity
+    }
 
-#[derive(Debug, PartialEq, Clone)]
-pub struct Item {
-    name: String,
-    quantity: u32,
+    pub fn as_equippable(&self) -> Option<Equippable> {
+        match &self.category {
+            ItemCategory::Weapon(weapon) => Some(Equippable::Weapon(weapon.clone())),
+            ItemCategory::Armor(armor) => Some(Equippable::Armor(armor.clone())),
+            ItemCategory::Wondrous(item) => Some(Equippable::Wondrous(item.clone())),
+            _ => None,
+        }
+    }
 }
 
+#[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        builder::{AbilityScores, CharacterBuilder},
+        currency::Denomination,
+        effect::{Buff, DamageKind, Duration, DurationUnit},
+        items::{WeaponKind, WeaponProperty},
+        leveling::LevelUp,
+    };
+    fn test_character() -> Character {
+        Character {
+            id: 1,
+            name: String::from("Test Character"),
+            race: Race::Human,
+            subrace: None,
+            background: None,
+            classes: vec![ClassDetails {
+                kind: Class::Fighter,
+                name: String::from("Fighter"),
+                level: 1,
+                hit_dice: 10,
+                hit_dice_spent: 0,
+                saving_throws: vec![SavingThrow::Strength, SavingThrow::Constitution],
+                subclass: None,
+                fighting_styles: vec![],
+                spells_known: vec![],
+            }],
+            advancement: Advancement::Experience(0),
+            feats: vec![],
+            expended_spell_slots: [0; 9],
+            resources_spent: HashMap::new(),
+            inspiration: None,
+            active_effects: vec![],
+            abilities: Abilities {
+                strength: Ability { value: 10 },
+                dexterity: Ability { value: 10 },
+                constitution: Ability { value: 10 },
+                intelligence: Ability { value: 10 },
+                wisdom: Ability { value: 10 },
+                charisma: Ability { value: 10 },
+            },
+            skills: Skills {
+                acrobatics: Skill {
+                    kind: SkillType::Acrobatics,
+                    value: 0,
+                },
+                animal_handling: Skill {
+                    kind: SkillType::AnimalHandling,
+                    value: 0,
+                },
+                arcana: Skill {
+                    kind: SkillType::Arcana,
+                    value: 0,
+                },
+                athletics: Skill {
+                    kind: SkillType::Athletics,
+                    value: 0,
+                },
+                deception: Skill {
+                    kind: SkillType::Deception,
+                    value: 0,
+                },
+                history: Skill {
+                    kind: SkillType::History,
+                    value: 0,
+                },
+                insight: Skill {
+                    kind: SkillType::Insight,
+                    value: 0,
+                },
+                intimidation: Skill {
+                    kind: SkillType::Intimidation,
+                    value: 0,
+                },
+                investigation: Skill {
+                    kind: SkillType::Investigation,
+                    value: 0,
+                },
+                medicine: Skill {
+                    kind: SkillType::Medicine,
+                    value: 0,
+                },
+                nature: Skill {
+                    kind: SkillType::Nature,
+                    value: 0,
+                },
+                perception: Skill {
+                    kind: SkillType::Perception,
+                    value: 0,
+                },
+                performance: Skill {
+                    kind: SkillType::Performance,
+                    value: 0,
+                },
+                persuasion: Skill {
+                    kind: SkillType::Persuasion,
+                    value: 0,
+                },
+                religion: Skill {
+                    kind: SkillType::Religion,
+                    value: 0,
+                },
+                sleight_of_hand: Skill {
+                    kind: SkillType::SleightOfHand,
+                    value: 0,
+                },
+                stealth: Skill {
+                    kind: SkillType::Stealth,
+                    value: 0,
+                },
+                survival: Skill {
+                    kind: SkillType::Survival,
+                    value: 0,
+                },
+            },
+            proficiencies: SkillProficiencies {
+                acrobatics: Proficiency::NotProficient,
+                animal_handling: Proficiency::NotProficient,
+                arcana: Proficiency::NotProficient,
+                athletics: Proficiency::NotProficient,
+                deception: Proficiency::NotProficient,
+                history: Proficiency::NotProficient,
+                insight: Proficiency::NotProficient,
+                intimidation: Proficiency::NotProficient,
+                investigation: Proficiency::NotProficient,
+                medicine: Proficiency::NotProficient,
+                nature: Proficiency::NotProficient,
+                perception: Proficiency::NotProficient,
+                performance: Proficiency::NotProficient,
+                persuasion: Proficiency::NotProficient,
+                religion: Proficiency::NotProficient,
+                sleight_of_hand: Proficiency::NotProficient,
+                stealth: Proficiency::NotProficient,
+                survival: Proficiency::NotProficient,
+            },
+            weapon_proficiencies: vec![],
+            armor_proficiencies: vec![],
+            tool_proficiencies: vec![],
+            saving_throw_proficiencies: vec![SavingThrow::Strength, SavingThrow::Constitution],
+            inventory: Inventory {
+                items: vec![],
+                purse: Purse::default(),
+            },
+            equipment: Equipment::default(),
+            hit_points: HitPoints {
+                current: 10,
+                max: 10,
+                temporary: 0,
+            },
+            armor_class: 10,
+            speed: 30,
+            variant_encumbrance: false,
+            darkvision: 0,
+            languages: vec![],
+            resistances: vec![],
+            traits: vec![],
+        }
+    }
+
     #[test]
     fn test_character_creation() {
         let character = Character {
             id: 1,
             name: String::from("Test Character"),
             race: Race::Human,
-            class: ClassDetails {
+            subrace: None,
+            background: None,
+            classes: vec![ClassDetails {
+                kind: Class::Fighter,
                 name: String::from("Fighter"),
+                level: 1,
                 hit_dice: 10,
+                hit_dice_spent: 0,
                 saving_throws: vec![SavingThrow::Strength, SavingThrow::Constitution],
-            },
+                subclass: None,
+                fighting_styles: vec![],
+                spells_known: vec![],
+            }],
+            advancement: Advancement::Experience(0),
+            feats: vec![],
+            expended_spell_slots: [0; 9],
+            resources_spent: HashMap::new(),
+            inspiration: None,
             active_effects: vec![],
-            level: 1,
             abilities: Abilities {
                 strength: Ability { value: 10 },
                 dexterity: Ability { value: 10 },
@@ -383,26 +2208,46 @@ mod tests {
                 stealth: Proficiency::NotProficient,
                 survival: Proficiency::NotProficient,
             },
-            inventory: Inventory { items: vec![] },
+            weapon_proficiencies: vec![],
+            armor_proficiencies: vec![],
+            tool_proficiencies: vec![],
+            saving_throw_proficiencies: vec![SavingThrow::Strength, SavingThrow::Constitution],
+            inventory: Inventory {
+                items: vec![],
+                purse: Purse::default(),
+            },
+            equipment: Equipment::default(),
             hit_points: HitPoints {
                 current: 10,
                 max: 10,
                 temporary: 0,
             },
             armor_class: 10,
+            speed: 30,
+            variant_encumbrance: false,
+            darkvision: 0,
+            languages: vec![],
+            resistances: vec![],
+            traits: vec![],
         };
 
         assert_eq!(character.name, "Test Character");
         assert_eq!(character.race, Race::Human);
         assert_eq!(
-            character.class,
-            ClassDetails {
+            character.classes,
+            vec![ClassDetails {
+                kind: Class::Fighter,
                 name: String::from("Fighter"),
+                level: 1,
                 hit_dice: 10,
+                hit_dice_spent: 0,
                 saving_throws: vec![SavingThrow::Strength, SavingThrow::Constitution],
-            }
+                subclass: None,
+                fighting_styles: vec![],
+                spells_known: vec![],
+            }]
         );
-        assert_eq!(character.level, 1);
+        assert_eq!(character.level(), 1);
         assert_eq!(character.abilities.strength, Ability { value: 10 });
         assert_eq!(
             character.skills.acrobatics,
@@ -432,13 +2277,25 @@ mod tests {
             id: 1,
             name: String::from("Test Character"),
             race: Race::Human,
-            class: ClassDetails {
+            subrace: None,
+            background: None,
+            classes: vec![ClassDetails {
+                kind: Class::Fighter,
                 name: String::from("Fighter"),
+                level: 1,
                 hit_dice: 10,
+                hit_dice_spent: 0,
                 saving_throws: vec![SavingThrow::Strength, SavingThrow::Constitution],
-            },
+                subclass: None,
+                fighting_styles: vec![],
+                spells_known: vec![],
+            }],
+            advancement: Advancement::Experience(0),
+            feats: vec![],
+            expended_spell_slots: [0; 9],
+            resources_spent: HashMap::new(),
+            inspiration: None,
             active_effects: vec![],
-            level: 1,
             abilities: Abilities {
                 strength: Ability { value: 10 },
                 dexterity: Ability { value: 12 },
@@ -541,21 +2398,414 @@ mod tests {
                 stealth: Proficiency::NotProficient,
                 survival: Proficiency::NotProficient,
             },
-            inventory: Inventory { items: vec![] },
+            weapon_proficiencies: vec![],
+            armor_proficiencies: vec![],
+            tool_proficiencies: vec![],
+            saving_throw_proficiencies: vec![SavingThrow::Strength, SavingThrow::Constitution],
+            inventory: Inventory {
+                items: vec![],
+                purse: Purse::default(),
+            },
+            equipment: Equipment::default(),
             hit_points: HitPoints {
                 current: 10,
                 max: 10,
                 temporary: 10,
             },
             armor_class: 10,
+            speed: 30,
+            variant_encumbrance: false,
+            darkvision: 0,
+            languages: vec![],
+            resistances: vec![],
+            traits: vec![],
         };
 
         // Test saving throw for each ability
-        assert_eq!(character.saving_throw(SavingThrow::Strength), 1);
+        assert_eq!(character.saving_throw(SavingThrow::Strength), 2);
         assert_eq!(character.saving_throw(SavingThrow::Dexterity), 1);
-        assert_eq!(character.saving_throw(SavingThrow::Constitution), 3);
+        assert_eq!(character.saving_throw(SavingThrow::Constitution), 4);
         assert_eq!(character.saving_throw(SavingThrow::Intelligence), 3);
         assert_eq!(character.saving_throw(SavingThrow::Wisdom), 4);
         assert_eq!(character.saving_throw(SavingThrow::Charisma), 5);
     }
+
+    #[test]
+    fn test_lucky_rerolls_each_die() {
+        let mut character = test_character();
+        character.traits.push(Trait {
+            name: String::from("Lucky"),
+            description: String::from("Reroll a natural 1."),
+        });
+
+        let mut rerolled = 0;
+        for seed in 0..200 {
+            seed_rng(seed);
+            let dice = Dice {
+                count: 3,
+                sides: 20,
+            };
+            let raw = dice.roll().rolls;
+            if raw[0] == 1 && raw[1] == 1 {
+                continue;
+            }
+
+            // A 1 on either die is replaced by the next roll, even when the other die would
+            // have been picked with disadvantage anyway.
+            let expected: Vec<i32> = raw[..2]
+                .iter()
+                .map(|roll| if *roll == 1 { raw[2] } else { *roll })
+                .collect();
+            rerolled += usize::from(expected != raw[..2]);
+            seed_rng(seed);
+            let roll = character.roll_d20(RollMode::Disadvantage);
+            assert_eq!(roll.total, *expected.iter().min().unwrap());
+            assert_eq!(roll.rolls, expected);
+        }
+        assert!(rerolled > 0);
+
+        let modifier = character.abilities.strength.get_modifier();
+        let mut rerolled = 0;
+        for seed in 0..100 {
+            seed_rng(seed);
+            let raw = Dice {
+                count: 2,
+                sides: 20,
+            }
+            .roll()
+            .rolls;
+            let natural = if raw[0] == 1 { raw[1] } else { raw[0] };
+            rerolled += usize::from(raw[0] == 1);
+            seed_rng(seed);
+            assert_eq!(
+                character.ability_check(&SavingThrow::Strength),
+                natural + modifier
+            );
+        }
+        assert!(rerolled > 0);
+    }
+
+    #[test]
+    fn test_armor_class_follows_equipment() {
+        let mut character = test_character();
+        character.abilities.dexterity = Ability { value: 16 };
+        character.abilities.strength = Ability { value: 12 };
+        character.update_armor_class();
+        assert_eq!(character.armor_class, 13);
+
+        let plate = Armor::new("Plate", ArmorCategory::Heavy, 18)
+            .with_strength_requirement(15)
+            .with_stealth_disadvantage();
+        assert_eq!(
+            character.equip(Equippable::Armor(plate.clone()), EquipmentSlot::Armor),
+            Err(EquipError::NotProficient(String::from("Plate")))
+        );
+
+        character.armor_proficiencies = vec![ArmorCategory::Heavy, ArmorCategory::Shield];
+        character
+            .equip(Equippable::Armor(plate), EquipmentSlot::Armor)
+            .unwrap();
+        character
+            .equip(
+                Equippable::Armor(Armor::new("Shield", ArmorCategory::Shield, 2)),
+                EquipmentSlot::Shield,
+            )
+            .unwrap();
+        assert_eq!(character.armor_class, 20);
+        assert_eq!(character.current_speed(), 20);
+        assert_eq!(
+            character.skill_check_mode(&SkillType::Stealth),
+            RollMode::Disadvantage
+        );
+
+        character.unequip(EquipmentSlot::Armor);
+        character.classes = vec![ClassDetails::new(&Class::Barbarian)];
+        character.abilities.constitution = Ability { value: 14 };
+        character.update_armor_class();
+        assert_eq!(character.armor_class, 17);
+        assert_eq!(character.current_speed(), 30);
+
+        let ring = WondrousItem {
+            name: String::from("Ring of Protection"),
+            slot: EquipmentSlot::LeftRing,
+            requires_attunement: true,
+            effects: vec![Effect::Buff(Buff {
+                kind: BuffKind::Bonus(EnhancementBuff::ArmorClass, 1),
+                duration: Duration {
+                    amount: 0,
+                    unit: DurationUnit::Immediate,
+                },
+            })],
+        };
+        character
+            .equip(Equippable::Wondrous(ring), EquipmentSlot::RightRing)
+            .unwrap();
+        assert_eq!(character.armor_class, 17);
+        character.attune("Ring of Protection").unwrap();
+        assert_eq!(character.armor_class, 18);
+    }
+
+    #[test]
+    fn test_inventory_stacking_and_totals() {
+        let mut inventory = Inventory {
+            items: vec![],
+            purse: Purse::default(),
+        };
+        let torch = || {
+            Item::new("Torch", ItemCategory::Gear)
+                .with_weight(1.0)
+                .with_cost(1)
+        };
+        inventory.add(torch().with_quantity(5));
+        inventory.add(torch().with_quantity(3));
+        assert_eq!(inventory.items.len(), 1);
+        assert_eq!(inventory.quantity_of("Torch"), 8);
+
+        let dagger = Weapon::new(
+            "Dagger",
+            WeaponCategory::Simple,
+            WeaponKind::Melee,
+            Dice { count: 1, sides: 4 },
+            DamageKind::Piercing,
+        )
+        .with_properties(vec![WeaponProperty::Finesse, WeaponProperty::Light]);
+        inventory.add(
+            Item::new("Dagger", ItemCategory::Weapon(dagger.clone()))
+                .with_weight(1.0)
+                .with_cost(200),
+        );
+        inventory.add(
+            Item::new("Dagger", ItemCategory::Weapon(dagger))
+                .with_weight(1.0)
+                .with_cost(200),
+        );
+        assert_eq!(inventory.items.len(), 3);
+        assert_eq!(inventory.total_weight(), 10.0);
+        assert_eq!(inventory.total_value(), 408);
+
+        let split = inventory.split("Torch", 2).unwrap();
+        assert_eq!(split.quantity, 2);
+        assert_eq!(inventory.items.len(), 4);
+        assert_eq!(inventory.quantity_of("Torch"), 8);
+        assert_eq!(inventory.items_in(&ItemCategory::Gear).len(), 2);
+
+        let removed = inventory.remove("Torch", 6).unwrap();
+        assert_eq!(removed.quantity, 6);
+        assert_eq!(inventory.quantity_of("Torch"), 2);
+    }
+
+    #[test]
+    fn test_encumbrance_slows_character() {
+        let mut character = test_character();
+        character.abilities.strength = Ability { value: 10 };
+        character.variant_encumbrance = true;
+        assert_eq!(character.carrying_capacity(), 150.0);
+
+        character.inventory.add(
+            Item::new("Iron Ingot", ItemCategory::Gear)
+                .with_weight(10.0)
+                .with_quantity(6),
+        );
+        assert_eq!(character.encumbrance(), Encumbrance::Encumbered);
+        assert_eq!(character.current_speed(), 20);
+
+        character
+            .inventory
+            .purse
+            .deposit(Denomination::Gold, 2500, "Hoard")
+            .unwrap();
+        assert_eq!(character.encumbrance(), Encumbrance::HeavilyEncumbered);
+        assert_eq!(character.current_speed(), 10);
+        assert_eq!(character.attack_roll_mode(), RollMode::Disadvantage);
+        assert_eq!(
+            character.saving_throw_mode(&SavingThrow::Wisdom),
+            RollMode::Normal
+        );
+        assert_eq!(
+            character.ability_check_mode(&SavingThrow::Constitution),
+            RollMode::Disadvantage
+        );
+        assert_eq!(
+            character.ability_check_mode(&SavingThrow::Intelligence),
+            RollMode::Normal
+        );
+
+        character.inventory.add(
+            Item::new("Iron Ingot", ItemCategory::Gear)
+                .with_weight(10.0)
+                .with_quantity(5),
+        );
+        assert_eq!(character.current_speed(), 5);
+        character.abilities.strength = Ability { value: 5 };
+        assert_eq!(character.current_speed(), 0);
+    }
+
+    #[test]
+    fn test_spell_attack_bonus() {
+        let cleric = CharacterBuilder::new("Jozan")
+            .with_race(Race::Human)
+            .with_class(Class::Cleric)
+            .with_ability_scores(AbilityScores::StandardArray([8, 10, 13, 12, 15, 14]))
+            .with_class_skills(vec![SkillType::Insight, SkillType::Religion])
+            .with_subclass("Life Domain")
+            .build(1)
+            .unwrap();
+        // Wisdom 16, not Intelligence 13.
+        assert_eq!(cleric.spell_attack_bonus(), 3 + 2);
+        assert_eq!(test_character().spell_attack_bonus(), 2);
+    }
+
+    #[test]
+    fn test_multiclassing() {
+        let mut character = CharacterBuilder::new("Thokk")
+            .with_race(Race::HalfOrc)
+            .with_class(Class::Fighter)
+            .with_level(3)
+            .with_ability_scores(AbilityScores::StandardArray([15, 13, 14, 8, 12, 10]))
+            .with_class_skills(vec![SkillType::Athletics, SkillType::Perception])
+            .with_subclass("Eldritch Knight")
+            .with_fighting_style(FightingStyle::Defense)
+            .build(1)
+            .unwrap();
+        assert_eq!(character.spell_slots(), [2, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+        assert_eq!(
+            character.add_class_level(&Class::Wizard, &[]),
+            Err(MulticlassError::PrerequisiteNotMet(Class::Wizard))
+        );
+        assert_eq!(
+            character.add_class_level(&Class::Rogue, &[SkillType::Arcana]),
+            Err(MulticlassError::InvalidSkillChoices)
+        );
+        character.gain_experience(1800);
+        LevelUp::new(Class::Rogue)
+            .with_skills(vec![SkillType::Stealth])
+            .apply(&mut character)
+            .unwrap();
+        assert_eq!(character.level(), 4);
+        assert_eq!(character.class_level(&Class::Rogue), 1);
+        assert_eq!(character.hit_points.max, 35);
+        assert_eq!(character.proficiencies.stealth, Proficiency::Proficient);
+        assert_eq!(
+            character.hit_dice(),
+            vec![
+                Dice {
+                    count: 3,
+                    sides: 10
+                },
+                Dice { count: 1, sides: 8 }
+            ]
+        );
+        assert!(character.sneak_attack_dice().is_some());
+
+        character.add_class_level(&Class::Fighter, &[]).unwrap();
+        assert_eq!(character.proficiency_bonus(), 3);
+        assert_eq!(character.attacks_per_action(), 1);
+        character.add_class_level(&Class::Fighter, &[]).unwrap();
+        assert_eq!(character.attacks_per_action(), 2);
+
+        // Wizard 3 and paladin 4 make a 5th level caster; the warlock's pact slots come on top.
+        let mut caster = character.clone();
+        caster.classes = vec![
+            ClassDetails::new(&Class::Wizard),
+            ClassDetails::new(&Class::Paladin),
+            ClassDetails::new(&Class::Warlock),
+        ];
+        for (class, level) in caster.classes.iter_mut().zip([3, 4, 2]) {
+            class.level = level;
+        }
+        assert_eq!(caster.spell_slots(), [6, 3, 2, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_feats() {
+        let mut character = CharacterBuilder::new("Akmenos")
+            .with_race(Race::Tiefling)
+            .with_class(Class::Wizard)
+            .with_class_skills(vec![SkillType::Arcana, SkillType::History])
+            .with_ability_scores(AbilityScores::StandardArray([8, 14, 15, 13, 12, 10]))
+            .build(1)
+            .unwrap();
+        let hit_points = character.hit_points.max;
+        let speed = character.current_speed();
+
+        assert_eq!(
+            character.take_feat("Heavily Armored", &FeatChoices::default()),
+            Err(FeatError::PrerequisiteNotMet(
+                FeatPrerequisite::ArmorProficiency(ArmorCategory::Medium)
+            ))
+        );
+        assert_eq!(
+            character.take_feat("Lightly Armored", &FeatChoices::default()),
+            Err(FeatError::InvalidAbilityChoice)
+        );
+        character
+            .take_feat(
+                "Lightly Armored",
+                &FeatChoices {
+                    ability: Some(SavingThrow::Dexterity),
+                    ..FeatChoices::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(character.abilities.dexterity.value, 15);
+        assert!(character
+            .armor_proficiencies
+            .contains(&ArmorCategory::Light));
+
+        assert!(!character.is_resistant_to(&DamageKind::Cold));
+        character
+            .take_feat("Infernal Constitution", &FeatChoices::default())
+            .unwrap();
+        assert!(character.is_resistant_to(&DamageKind::Cold));
+        assert_eq!(character.abilities.constitution.value, 16);
+        assert_eq!(character.hit_points.max, hit_points + 1);
+        assert_eq!(
+            character.take_feat("Infernal Constitution", &FeatChoices::default()),
+            Err(FeatError::AlreadyTaken(String::from(
+                "Infernal Constitution"
+            )))
+        );
+
+        character
+            .take_feat("Tough", &FeatChoices::default())
+            .unwrap();
+        character
+            .take_feat("Mobile", &FeatChoices::default())
+            .unwrap();
+        assert_eq!(character.hit_points.max, hit_points + 3);
+        assert_eq!(character.current_speed(), speed + 10);
+
+        let greatsword = Weapon::new(
+            "Greatsword",
+            WeaponCategory::Martial,
+            WeaponKind::Melee,
+            Dice { count: 2, sides: 6 },
+            DamageKind::Slashing,
+        )
+        .with_properties(vec![WeaponProperty::Heavy, WeaponProperty::TwoHanded]);
+        character
+            .take_feat("Great Weapon Master", &FeatChoices::default())
+            .unwrap();
+        assert!(!character.can_power_attack(&greatsword));
+        character
+            .weapon_proficiencies
+            .push(WeaponProficiency::Martial);
+        assert!(character.can_power_attack(&greatsword));
+
+        let constitution_save = character.saving_throw(SavingThrow::Constitution);
+        character
+            .take_feat(
+                "Resilient",
+                &FeatChoices {
+                    ability: Some(SavingThrow::Constitution),
+                    ..FeatChoices::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            character.saving_throw(SavingThrow::Constitution),
+            constitution_save + 2
+        );
+    }
 }
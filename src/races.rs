@@ -0,0 +1,97 @@
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    effect::DamageKind,
+    items::{ArmorCategory, WeaponProficiency},
+    models::{Race, SavingThrow, Size, SkillType},
+};
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Trait {
+    pub name: String,
+    pub description: String,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Subrace {
+    pub name: String,
+    pub ability_bonuses: Vec<(SavingThrow, u32)>,
+    // Replaces the race's speed and darkvision when set.
+    #[serde(default)]
+    pub speed: Option<u32>,
+    #[serde(default)]
+    pub darkvision: Option<u32>,
+    #[serde(default)]
+    pub hit_points_per_level: i32,
+    #[serde(default)]
+    pub resistances: Vec<DamageKind>,
+    #[serde(default)]
+    pub weapon_proficiencies: Vec<WeaponProficiency>,
+    #[serde(default)]
+    pub armor_proficiencies: Vec<ArmorCategory>,
+    pub traits: Vec<Trait>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct RaceDefinition {
+    pub race: Race,
+    pub ability_bonuses: Vec<(SavingThrow, u32)>,
+    // Number of different abilities of the player's choice that get +1, e.g. for a half-elf.
+    #[serde(default)]
+    pub ability_choices: usize,
+    // Number of skill proficiencies of the player's choice.
+    #[serde(default)]
+    pub skill_choices: usize,
+    pub size: Size,
+    pub speed: u32,
+    #[serde(default)]
+    pub darkvision: u32,
+    pub languages: Vec<String>,
+    #[serde(default)]
+    pub resistances: Vec<DamageKind>,
+    #[serde(default)]
+    pub skill_proficiencies: Vec<SkillType>,
+    #[serde(default)]
+    pub weapon_proficiencies: Vec<WeaponProficiency>,
+    pub traits: Vec<Trait>,
+    pub subraces: Vec<Subrace>,
+}
+
+impl RaceDefinition {
+    pub fn subrace(&self, name: &str) -> Option<&Subrace> {
+        self.subraces.iter().find(|subrace| subrace.name == name)
+    }
+}
+
+pub fn races() -> &'static Vec<RaceDefinition> {
+    static RACES: OnceLock<Vec<RaceDefinition>> = OnceLock::new();
+    RACES.get_or_init(|| serde_json::from_str(include_str!("../data/races.json")).unwrap())
+}
+
+pub fn race_definition(race: &Race) -> &'static RaceDefinition {
+    races()
+        .iter()
+        .find(|definition| definition.race == *race)
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_race_data() {
+        assert_eq!(races().len(), 8);
+
+        let dwarf = race_definition(&Race::Dwarf);
+        assert_eq!(dwarf.speed, 25);
+        assert_eq!(dwarf.resistances, vec![DamageKind::Poison]);
+        let hill_dwarf = dwarf.subrace("Hill Dwarf").unwrap();
+        assert_eq!(hill_dwarf.hit_points_per_level, 1);
+
+        assert_eq!(race_definition(&Race::Gnome).size, Size::Small);
+        assert_eq!(race_definition(&Race::HalfElf).ability_choices, 2);
+    }
+}
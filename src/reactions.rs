@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+
+use crate::actions::{Action, ActionResult, Attack};
+
+// Points during the resolution of another creature's action where reactions can interrupt.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Trigger {
+    LeavesReach {
+        mover_id: u32,
+        reactor_id: u32,
+    },
+    Moves {
+        mover_id: u32,
+    },
+    Attacks {
+        attacker_id: u32,
+        target_id: u32,
+    },
+    Hit {
+        attacker_id: u32,
+        target_id: u32,
+        attack_roll: i32,
+    },
+    CastsSpell {
+        caster_id: u32,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ReadyTrigger {
+    CreatureMoves,
+    CreatureAttacks,
+    CreatureCastsSpell,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ReactionKind {
+    // The attack's target is filled in when the reaction triggers.
+    OpportunityAttack(Attack),
+    Shield,
+    UncannyDodge,
+    // Granted by the Defensive Duelist feat rather than registered.
+    DefensiveDuelist,
+    Counterspell,
+    Readied {
+        trigger: ReadyTrigger,
+        action: Box<Action>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Reaction {
+    pub owner_id: u32,
+    pub kind: ReactionKind,
+}
+
+impl Reaction {
+    // Whether the trigger is the kind of event this reaction responds to. Conditions that
+    // depend on the rest of the state (hostility, distance, sight) are checked by `State`.
+    pub fn responds_to(&self, trigger: &Trigger) -> bool {
+        match (&self.kind, trigger) {
+            (ReactionKind::OpportunityAttack(_), Trigger::LeavesReach { reactor_id, .. }) => {
+                *reactor_id == self.owner_id
+            }
+            (
+                ReactionKind::Shield | ReactionKind::UncannyDodge | ReactionKind::DefensiveDuelist,
+                Trigger::Hit { target_id, .. },
+            ) => *target_id == self.owner_id,
+            (ReactionKind::Counterspell, Trigger::CastsSpell { caster_id }) => {
+                *caster_id != self.owner_id
+            }
+            (ReactionKind::Readied { trigger: ready, .. }, trigger) => {
+                let actor_id = match (ready, trigger) {
+                    (ReadyTrigger::CreatureMoves, Trigger::Moves { mover_id }) => mover_id,
+                    (ReadyTrigger::CreatureAttacks, Trigger::Attacks { attacker_id, .. }) => {
+                        attacker_id
+                    }
+                    (ReadyTrigger::CreatureCastsSpell, Trigger::CastsSpell { caster_id }) => {
+                        caster_id
+                    }
+                    _ => return false,
+                };
+                *actor_id != self.owner_id
+            }
+            _ => false,
+        }
+    }
+
+    // Readied actions are used up once they fire; the other reactions stay registered and
+    // are limited only by the owner's one reaction per round.
+    pub fn is_one_shot(&self) -> bool {
+        matches!(self.kind, ReactionKind::Readied { .. })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReactionOutcome {
+    pub reactor_id: u32,
+    pub kind: ReactionKind,
+    pub result: ActionResult,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reaction_responds_to_trigger() {
+        let shield = Reaction {
+            owner_id: 2,
+            kind: ReactionKind::Shield,
+        };
+        assert!(shield.responds_to(&Trigger::Hit {
+            attacker_id: 1,
+            target_id: 2,
+            attack_roll: 15,
+        }));
+        assert!(!shield.responds_to(&Trigger::Hit {
+            attacker_id: 2,
+            target_id: 1,
+            attack_roll: 15,
+        }));
+
+        let readied = Reaction {
+            owner_id: 1,
+            kind: ReactionKind::Readied {
+                trigger: ReadyTrigger::CreatureMoves,
+                action: Box::new(Action::Dodge),
+            },
+        };
+        assert!(readied.responds_to(&Trigger::Moves { mover_id: 2 }));
+        assert!(!readied.responds_to(&Trigger::Moves { mover_id: 1 }));
+        assert!(!readied.responds_to(&Trigger::CastsSpell { caster_id: 2 }));
+    }
+}
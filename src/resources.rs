@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Abilities, SavingThrow};
+
+// Sorcery points it costs to create a spell slot of each level with Font of Magic.
+pub const SPELL_SLOT_COSTS: [u32; 5] = [2, 3, 5, 6, 7];
+// Maximum of a pool that can be used any number of times.
+pub const UNLIMITED: u32 = u32::MAX;
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum ResourceMaximum {
+    // One per class level, e.g. ki points.
+    ClassLevel,
+    // Maximum from each class level on, e.g. (3, 3) for three rages from 3rd level.
+    ByLevel(Vec<(u32, u32)>),
+    // The ability's modifier, at least 1.
+    AbilityModifier(SavingThrow),
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum Recovery {
+    ShortRest,
+    LongRest,
+}
+
+// Pool of uses of a limited class feature.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Resource {
+    pub name: String,
+    pub maximum: ResourceMaximum,
+    pub recovery: Recovery,
+    // Class level from which a long rest pool also recovers on a short rest, e.g. with Font
+    // of Inspiration.
+    #[serde(default)]
+    pub short_rest_level: Option<u32>,
+    // Class level from which the pool has no limit, e.g. a barbarian's rages at 20th level.
+    #[serde(default)]
+    pub unlimited_level: Option<u32>,
+}
+
+impl Resource {
+    pub fn maximum(&self, class_level: u32, abilities: &Abilities) -> u32 {
+        if self
+            .unlimited_level
+            .is_some_and(|level| class_level >= level)
+        {
+            return UNLIMITED;
+        }
+
+        match &self.maximum {
+            ResourceMaximum::ClassLevel => class_level,
+            ResourceMaximum::ByLevel(steps) => steps
+                .iter()
+                .rev()
+                .find(|(level, _)| *level <= class_level)
+                .map(|(_, maximum)| *maximum)
+                .unwrap_or(0),
+            ResourceMaximum::AbilityModifier(ability) => {
+                abilities.get(ability).get_modifier().max(1) as u32
+            }
+        }
+    }
+
+    pub fn recovers_on_short_rest(&self, class_level: u32) -> bool {
+        self.recovery == Recovery::ShortRest
+            || self
+                .short_rest_level
+                .is_some_and(|level| class_level >= level)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ResourceError {
+    UnknownResource(String),
+    NotEnough { remaining: u32 },
+    // Font of Magic creates slots up to 5th level, replacing expended ones.
+    InvalidSpellSlot(u32),
+    NoExpendedSlot(u32),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        classes::{class_definition, FeatureEffect},
+        models::{Ability, Class},
+    };
+
+    #[test]
+    fn test_resource_maximum() {
+        let rage = class_definition(&Class::Barbarian)
+            .features
+            .iter()
+            .flat_map(|feature| &feature.effects)
+            .find_map(|effect| match effect {
+                FeatureEffect::Resource(resource) => Some(resource),
+                _ => None,
+            })
+            .unwrap();
+        let abilities = Abilities {
+            strength: Ability { value: 16 },
+            dexterity: Ability { value: 14 },
+            constitution: Ability { value: 14 },
+            intelligence: Ability { value: 8 },
+            wisdom: Ability { value: 10 },
+            charisma: Ability { value: 8 },
+        };
+        assert_eq!(rage.maximum(1, &abilities), 2);
+        assert_eq!(rage.maximum(5, &abilities), 3);
+        assert_eq!(rage.maximum(19, &abilities), 6);
+        assert_eq!(rage.maximum(20, &abilities), UNLIMITED);
+        assert!(!rage.recovers_on_short_rest(20));
+
+        let inspiration = Resource {
+            name: String::from("Bardic Inspiration"),
+            maximum: ResourceMaximum::AbilityModifier(SavingThrow::Charisma),
+            recovery: Recovery::LongRest,
+            short_rest_level: Some(5),
+            unlimited_level: None,
+        };
+        assert_eq!(inspiration.maximum(1, &abilities), 1);
+        assert!(inspiration.recovers_on_short_rest(5));
+    }
+}
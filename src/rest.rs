@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::Class;
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum RestKind {
+    // An hour of light activity, spending hit dice to heal.
+    Short,
+    // Eight hours of sleep and light activity, restoring hit points and spell slots.
+    Long,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum RestError {
+    AlreadyResting,
+    NotResting,
+    Interrupted,
+    UnknownCharacter(u32),
+    // Hit dice can only be spent by party members during a short rest.
+    NotShortRest,
+    NotInParty(u32),
+    NoHitDice(Class),
+}
+
+// Rest a party is taking. Fighting, casting spells or taking damage interrupts it, and an
+// interrupted rest gives no benefits.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Rest {
+    pub kind: RestKind,
+    pub party: Vec<u32>,
+    pub interrupted: bool,
+}
+
+impl Rest {
+    pub fn new(kind: RestKind, party: Vec<u32>) -> Self {
+        Self {
+            kind,
+            party,
+            interrupted: false,
+        }
+    }
+
+    pub fn includes(&self, character_id: u32) -> bool {
+        self.party.contains(&character_id)
+    }
+}
@@ -0,0 +1,254 @@
+use std::{fs, path::Path};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{models::Character, state::State};
+
+// Version of the save format this build writes. Older saves are migrated on load.
+pub const SAVE_VERSION: u32 = 2;
+
+// Upgrades a saved document from the version at its index to the next one.
+type Migration = fn(&mut Value) -> Result<(), SaveError>;
+
+// Version 0 saves were written before files carried a version, and only lack the envelope.
+const CHARACTER_MIGRATIONS: [Migration; SAVE_VERSION as usize] = [unchanged, unchanged];
+// Characters inside a session are migrated with the character chain as well.
+const SESSION_MIGRATIONS: [Migration; SAVE_VERSION as usize] = [unchanged, characters_by_id];
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum SaveError {
+    // The file couldn't be read or written.
+    Io(String),
+    // The file isn't valid JSON for what was being loaded.
+    Format(String),
+    // The file was saved by a newer version than this build can read.
+    NewerVersion { version: u32, supported: u32 },
+}
+
+#[derive(Serialize, Deserialize)]
+struct SaveFile<T> {
+    version: u32,
+    data: T,
+}
+
+fn unchanged(_: &mut Value) -> Result<(), SaveError> {
+    Ok(())
+}
+
+// Version 2 keys a session's characters by id and tracks the next id to hand out. Older
+// sessions could hold several characters with the same id, so repeats get fresh ids.
+fn characters_by_id(document: &mut Value) -> Result<(), SaveError> {
+    let Some(Value::Array(characters)) = document.get_mut("characters") else {
+        return Err(SaveError::Format(String::from("missing characters")));
+    };
+    let ids = characters
+        .iter()
+        .map(|character| character.get("id").and_then(Value::as_u64))
+        .collect::<Option<Vec<u64>>>()
+        .ok_or_else(|| SaveError::Format(String::from("character without an id")))?;
+
+    let mut next_id = ids.iter().max().map_or(1, |id| id + 1);
+    let mut by_id = Map::new();
+    for (mut character, mut id) in characters.drain(..).zip(ids) {
+        if by_id.contains_key(&id.to_string()) {
+            id = next_id;
+            next_id += 1;
+            character["id"] = Value::from(id);
+        }
+        by_id.insert(id.to_string(), character);
+    }
+    document["characters"] = Value::Object(by_id);
+    document["next_id"] = Value::from(next_id);
+    Ok(())
+}
+
+fn write_json<T: Serialize>(value: &T, path: &Path) -> Result<(), SaveError> {
+    let file = SaveFile {
+        version: SAVE_VERSION,
+        data: value,
+    };
+    let json = serde_json::to_string_pretty(&file)
+        .map_err(|error| SaveError::Format(error.to_string()))?;
+    fs::write(path, json).map_err(|error| SaveError::Io(error.to_string()))
+}
+
+// Splits a save into its version and document, treating files without a version as
+// version 0. A version with a malformed envelope is rejected rather than migrated.
+fn read_document(path: &Path) -> Result<(u32, Value), SaveError> {
+    let json = fs::read_to_string(path).map_err(|error| SaveError::Io(error.to_string()))?;
+    let document: Value =
+        serde_json::from_str(&json).map_err(|error| SaveError::Format(error.to_string()))?;
+    if document.get("version").is_none() {
+        return Ok((0, document));
+    }
+
+    let file: SaveFile<Value> =
+        serde_json::from_value(document).map_err(|error| SaveError::Format(error.to_string()))?;
+    if file.version > SAVE_VERSION {
+        return Err(SaveError::NewerVersion {
+            version: file.version,
+            supported: SAVE_VERSION,
+        });
+    }
+    Ok((file.version, file.data))
+}
+
+fn from_document<T: DeserializeOwned>(document: Value) -> Result<T, SaveError> {
+    serde_json::from_value(document).map_err(|error| SaveError::Format(error.to_string()))
+}
+
+fn migrate_character(document: &mut Value, version: u32) -> Result<(), SaveError> {
+    for migration in &CHARACTER_MIGRATIONS[version as usize..] {
+        migration(document)?;
+    }
+    Ok(())
+}
+
+fn migrate_session(document: &mut Value, version: u32) -> Result<(), SaveError> {
+    for (step, migration) in SESSION_MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let characters: Vec<&mut Value> = match document.get_mut("characters") {
+            Some(Value::Array(characters)) => characters.iter_mut().collect(),
+            Some(Value::Object(characters)) => characters.values_mut().collect(),
+            _ => vec![],
+        };
+        for character in characters {
+            CHARACTER_MIGRATIONS[step](character)?;
+        }
+        migration(document)?;
+    }
+    Ok(())
+}
+
+pub fn save_character(character: &Character, path: &Path) -> Result<(), SaveError> {
+    write_json(character, path)
+}
+
+pub fn load_character(path: &Path) -> Result<Character, SaveError> {
+    let (version, mut document) = read_document(path)?;
+    migrate_character(&mut document, version)?;
+    from_document(document)
+}
+
+// A session is the whole state: characters, map, teams, reactions and any rest in progress.
+pub fn save_session(state: &State, path: &Path) -> Result<(), SaveError> {
+    write_json(state, path)
+}
+
+pub fn load_session(path: &Path) -> Result<State, SaveError> {
+    let (version, mut document) = read_document(path)?;
+    migrate_session(&mut document, version)?;
+    from_document(document)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        builder::{AbilityScores, CharacterBuilder},
+        models::{Class, Race, SkillType},
+    };
+
+    fn barbarian(id: u32, name: &str) -> Character {
+        CharacterBuilder::new(name)
+            .with_race(Race::Human)
+            .with_class(Class::Barbarian)
+            .with_class_skills(vec![SkillType::Athletics, SkillType::Perception])
+            .with_ability_scores(AbilityScores::StandardArray([15, 14, 13, 12, 10, 8]))
+            .build(id)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_save_and_load_character() {
+        let mut character = CharacterBuilder::new("Elminster")
+            .with_race(Race::Human)
+            .with_class(Class::Wizard)
+            .with_class_skills(vec![SkillType::Insight, SkillType::Investigation])
+            .with_ability_scores(AbilityScores::StandardArray([8, 13, 14, 15, 12, 10]))
+            .with_background("Sage")
+            .with_languages(vec![String::from("Elvish"), String::from("Draconic")])
+            .build(1)
+            .unwrap();
+        character.expend_spell_slot(1).unwrap();
+
+        let path = env::temp_dir().join("vvt_test_character.json");
+        save_character(&character, &path).unwrap();
+        assert_eq!(load_character(&path), Ok(character.clone()));
+
+        // Saves from before the format was versioned hold the bare character.
+        fs::write(&path, serde_json::to_string(&character).unwrap()).unwrap();
+        assert_eq!(load_character(&path), Ok(character));
+
+        fs::write(&path, "{\"version\": 99, \"data\": {}}").unwrap();
+        assert_eq!(
+            load_character(&path),
+            Err(SaveError::NewerVersion {
+                version: 99,
+                supported: SAVE_VERSION
+            })
+        );
+
+        for malformed in ["{\"version\": \"2\", \"data\": {}}", "{\"version\": 2}"] {
+            fs::write(&path, malformed).unwrap();
+            assert!(matches!(load_character(&path), Err(SaveError::Format(_))));
+        }
+
+        fs::write(&path, "{\"name\": \"Elminster\"}").unwrap();
+        assert!(matches!(load_character(&path), Err(SaveError::Format(_))));
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(load_character(&path), Err(SaveError::Io(_))));
+    }
+
+    #[test]
+    fn test_session_migrations() {
+        let mut state = State::new();
+        state.add_character(barbarian(1, "Thokk")).unwrap();
+        let mut document = serde_json::to_value(&state).unwrap();
+        document.as_object_mut().unwrap().remove("next_id");
+        // Version 1 sessions listed their characters, and often gave every one id 1.
+        document["characters"] = json!([
+            barbarian(1, "Thokk"),
+            barbarian(1, "Grog"),
+            barbarian(4, "Lia"),
+            barbarian(1, "Vex")
+        ]);
+
+        let path = env::temp_dir().join("vvt_test_migrated_session.json");
+        fs::write(&path, json!({ "version": 1, "data": document }).to_string()).unwrap();
+        let mut loaded = load_session(&path).unwrap();
+        let names: Vec<(u32, &str)> = loaded
+            .characters()
+            .map(|character| (character.id, character.name.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![(1, "Thokk"), (4, "Lia"), (5, "Grog"), (6, "Vex")]
+        );
+        assert_eq!(loaded.allocate_id(), 7);
+
+        // Unversioned sessions go through the whole chain.
+        fs::write(&path, document.to_string()).unwrap();
+        assert_eq!(load_session(&path).unwrap().characters().count(), 4);
+
+        fs::write(&path, json!({ "version": 1, "data": {} }).to_string()).unwrap();
+        assert!(matches!(load_session(&path), Err(SaveError::Format(_))));
+        fs::write(
+            &path,
+            json!({ "version": SAVE_VERSION + 1, "data": {} }).to_string(),
+        )
+        .unwrap();
+        assert_eq!(
+            load_session(&path).err(),
+            Some(SaveError::NewerVersion {
+                version: SAVE_VERSION + 1,
+                supported: SAVE_VERSION
+            })
+        );
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,106 +1,1035 @@
-use std::io::{self, Write};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    io::{self, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    actions::Action,
-    effect::{BuffKind, Effect, EnhancementBuff},
+    actions::{
+        Action, ActionResult, Attack, CastSpell, FeatureAction, Outcome, UseFeature, UseObject,
+        WeaponAttack,
+    },
+    backgrounds::{background, backgrounds},
+    builder::{
+        roll_ability_scores, AbilityScores, CharacterBuilder, POINT_BUY_BUDGET, STANDARD_ARRAY,
+    },
+    classes::{classes, ClassFeature, FeatureEffect, FightingStyle, MulticlassError},
+    currency::Purse,
+    effect::{
+        Buff, BuffKind, ConditionKind, DamageKind, Duration, DurationUnit, Effect, EnhancementBuff,
+    },
+    feats::{
+        feat, FeatChoices, FeatEffect, FeatReaction, POWER_ATTACK_BONUS, POWER_ATTACK_PENALTY,
+    },
+    items::{WeaponKind, WeaponMode, WeaponProperty},
+    leveling::{AbilityScoreImprovement, Advancement, HitPointIncrease, LevelUp, LevelUpError},
+    map::{AttackRange, BattleMap, Cover, MoveError, Position, RangeCheck},
     models::{
-        Abilities, Ability, Character, Class, ClassDetails, Dice, HitPoints, Inventory,
-        Proficiency, Race, SavingThrow, Skill, SkillProficiencies, SkillType, Skills,
+        Character, Class, Dice, Item, ItemCategory, Proficiency, Race, RollMode, SavingThrow, Size,
+        SkillType,
     },
+    races::race_definition,
+    reactions::{Reaction, ReactionKind, ReactionOutcome, Trigger},
+    rest::{Rest, RestError, RestKind},
+    save::{load_character, load_session, save_character, save_session},
 };
 
+const COUNTERSPELL_RANGE: u32 = 60;
+const BARDIC_INSPIRATION_RANGE: u32 = 60;
+const ABILITY_NAMES: [&str; 6] = [
+    "strength",
+    "dexterity",
+    "constitution",
+    "intelligence",
+    "wisdom",
+    "charisma",
+];
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum StateError {
+    DuplicateId(u32),
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct State {
-    characters: Vec<Character>,
+    characters: BTreeMap<u32, Character>,
+    next_id: u32,
+    pub map: BattleMap,
+    pub party_fund: Purse,
+    reactions: Vec<Reaction>,
+    reactions_used: HashSet<u32>,
+    teams: HashMap<u32, u32>,
+    armor_class_bonus: HashMap<u32, i32>,
+    // Ammunition and thrown weapons used since the last recovery, by character.
+    spent_ammunition: HashMap<u32, Vec<Item>>,
+    // Characters who have dealt Sneak Attack damage this turn.
+    sneak_attack_used: HashSet<u32>,
+    rest: Option<Rest>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl State {
     pub fn new() -> Self {
         Self {
-            characters: Vec::new(),
+            characters: BTreeMap::new(),
+            next_id: 1,
+            map: BattleMap::new(100, 100),
+            party_fund: Purse::default(),
+            reactions: Vec::new(),
+            reactions_used: HashSet::new(),
+            teams: HashMap::new(),
+            armor_class_bonus: HashMap::new(),
+            spent_ammunition: HashMap::new(),
+            sneak_attack_used: HashSet::new(),
+            rest: None,
         }
     }
 
-    pub fn apply_action(&mut self, action: Action) {
-        match action {
-            Action::Attack(attack) => {
-                let attacker = self
-                    .characters
-                    .iter()
-                    .find(|character| character.id == attack.actor_id)
-                    .unwrap();
+    pub fn start_turn(&mut self, character_id: u32) {
+        self.reactions_used.remove(&character_id);
+        self.armor_class_bonus.remove(&character_id);
+        self.sneak_attack_used.remove(&character_id);
+        if let Some(character) = self.characters.get(&character_id) {
+            self.map.start_turn(character_id, character.current_speed());
+        }
+    }
 
-                let attack_roll = Dice {
-                    count: 1,
-                    sides: 20,
+    // Hands out an id no character in the session has used yet.
+    pub fn allocate_id(&mut self) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    pub fn add_character(&mut self, character: Character) -> Result<(), StateError> {
+        if self.characters.contains_key(&character.id) {
+            return Err(StateError::DuplicateId(character.id));
+        }
+        self.next_id = self.next_id.max(character.id + 1);
+        self.characters.insert(character.id, character);
+        Ok(())
+    }
+
+    pub fn get_character(&self, id: u32) -> Option<&Character> {
+        self.characters.get(&id)
+    }
+
+    pub fn get_character_mut(&mut self, id: u32) -> Option<&mut Character> {
+        self.characters.get_mut(&id)
+    }
+
+    // Takes a character out of the session, along with its place on the map, team,
+    // reactions and any rest in progress.
+    pub fn remove_character(&mut self, id: u32) -> Option<Character> {
+        let character = self.characters.remove(&id)?;
+        self.map.remove(id);
+        self.teams.remove(&id);
+        self.reactions.retain(|reaction| reaction.owner_id != id);
+        self.reactions_used.remove(&id);
+        self.armor_class_bonus.remove(&id);
+        self.spent_ammunition.remove(&id);
+        self.sneak_attack_used.remove(&id);
+        if let Some(rest) = &mut self.rest {
+            rest.party.retain(|member| *member != id);
+        }
+        Some(character)
+    }
+
+    // Characters in the order of their ids.
+    pub fn characters(&self) -> impl Iterator<Item = &Character> {
+        self.characters.values()
+    }
+
+    pub fn set_team(&mut self, character_id: u32, team: u32) {
+        self.teams.insert(character_id, team);
+    }
+
+    // Creatures that haven't joined a team are hostile to everyone else.
+    pub fn are_hostile(&self, a: u32, b: u32) -> bool {
+        a != b
+            && match (self.teams.get(&a), self.teams.get(&b)) {
+                (Some(a), Some(b)) => a != b,
+                _ => true,
+            }
+    }
+
+    // Shares the party fund out evenly between the given characters. Coins that don't divide
+    // evenly stay in the fund, as does the share of a character whose purse can't hold it.
+    pub fn split_party_fund(&mut self, character_ids: &[u32]) {
+        let recipients: Vec<u32> = character_ids
+            .iter()
+            .copied()
+            .filter(|id| self.characters.contains_key(id))
+            .collect();
+        let shares = self
+            .party_fund
+            .split(recipients.len() as u32, "Party split");
+
+        for (id, share) in recipients.into_iter().zip(shares) {
+            let character = self.characters.get_mut(&id).unwrap();
+            if character
+                .inventory
+                .purse
+                .merge(share.clone(), "Party split")
+                .is_err()
+            {
+                // The fund held these coins a moment ago, so it can take them back.
+                self.party_fund.merge(share, "Party split").unwrap();
+            }
+        }
+    }
+
+    pub fn register_reaction(&mut self, reaction: Reaction) {
+        self.reactions.push(reaction);
+    }
+
+    pub fn has_reaction(&self, character_id: u32) -> bool {
+        !self.reactions_used.contains(&character_id)
+            && self.characters.get(&character_id).is_some_and(|c| {
+                c.hit_points.current > 0 && !c.has_condition(ConditionKind::Incapacitated)
+            })
+    }
+
+    // Moves a character one square at a time so that leaving a hostile creature's reach
+    // offers it an opportunity attack before the character steps away.
+    pub fn move_character(
+        &mut self,
+        character_id: u32,
+        path: &[Position],
+    ) -> Result<ActionResult, MoveError> {
+        let costs = self.map.path_costs(character_id, path, |other| {
+            self.are_hostile(character_id, other)
+        })?;
+        let size = self.map.placement(character_id).unwrap().size.clone();
+
+        let mut reactions = vec![];
+        let mut feet = 0;
+        let mut entered = false;
+        for (step, cost) in path.iter().zip(costs) {
+            let threats: Vec<u32> = self
+                .reactions
+                .iter()
+                .filter_map(|reaction| match &reaction.kind {
+                    ReactionKind::OpportunityAttack(Attack {
+                        range: AttackRange::Melee { reach },
+                        ..
+                    }) => Some((reaction.owner_id, *reach)),
+                    _ => None,
+                })
+                .filter(|(owner_id, reach)| {
+                    self.are_hostile(*owner_id, character_id)
+                        && self.map.within_reach(*owner_id, character_id, *reach) == Some(true)
+                        && self
+                            .map
+                            .distance_from(*step, &size, *owner_id)
+                            .is_some_and(|distance| distance > *reach)
+                })
+                .map(|(owner_id, _)| owner_id)
+                .collect();
+
+            for reactor_id in threats {
+                reactions.extend(self.trigger_reactions(Trigger::LeavesReach {
+                    mover_id: character_id,
+                    reactor_id,
+                }));
+            }
+            if !self.has_hit_points(character_id) {
+                break;
+            }
+
+            self.map.step(character_id, *step, cost);
+            feet += cost;
+            entered = true;
+        }
+
+        // Staying put isn't movement anything can react to.
+        if entered {
+            reactions.extend(self.trigger_reactions(Trigger::Moves {
+                mover_id: character_id,
+            }));
+        }
+
+        Ok(ActionResult {
+            outcome: Outcome::Moved { feet },
+            reactions,
+        })
+    }
+
+    pub fn can_see(&self, observer_id: u32, target_id: u32) -> bool {
+        let observer = self.characters.get(&observer_id);
+        let target = self.characters.get(&target_id);
+        if observer.is_some_and(|observer| observer.has_condition(ConditionKind::Blinded))
+            || target.is_some_and(|target| target.has_condition(ConditionKind::Invisible))
+        {
+            return false;
+        }
+
+        self.map
+            .has_line_of_sight(observer_id, target_id)
+            .unwrap_or(true)
+    }
+
+    // Cover between two creatures, or no cover when either of them isn't on the map.
+    pub fn cover(&self, source_id: u32, target_id: u32) -> Cover {
+        self.map.cover(source_id, target_id).unwrap_or(Cover::None)
+    }
+
+    // Rolls a saving throw against an effect from the source, with disadvantage when the
+    // character's encumbrance hampers the ability.
+    pub fn saving_throw(
+        &self,
+        character_id: u32,
+        saving_throw: SavingThrow,
+        source_id: u32,
+    ) -> Option<i32> {
+        let character = self.characters.get(&character_id)?;
+        let cover_bonus = match saving_throw {
+            SavingThrow::Dexterity => self.cover(source_id, character_id).bonus(),
+            _ => 0,
+        };
+
+        let roll = character.roll_d20(character.saving_throw_mode(&saving_throw));
+        Some(roll.total + character.saving_throw(saving_throw) + cover_bonus)
+    }
+
+    fn armor_class_against(&self, attacker_id: u32, target_id: u32) -> i32 {
+        let target = self.characters.get(&target_id).unwrap();
+
+        target.armor_class
+            + self.cover(attacker_id, target_id).bonus()
+            + self.armor_class_bonus.get(&target_id).unwrap_or(&0)
+    }
+
+    // Applies damage to a character and returns the amount taken.
+    fn deal_damage(&mut self, character_id: u32, amount: i32, kind: &DamageKind) -> i32 {
+        self.disturb_rest(character_id);
+        let target = self.characters.get_mut(&character_id).unwrap();
+
+        let amount = if target.is_resistant_to(kind) {
+            amount / 2
+        } else {
+            amount
+        };
+
+        target.hit_points.current -= amount;
+        if target.hit_points.current <= 0 {
+            target.hit_points.current = 0;
+        }
+
+        amount
+    }
+
+    // Applies an effect directly, without any attack roll or saving throw.
+    fn apply_effect(&mut self, target_id: u32, effect: Effect) -> Outcome {
+        let target = self.characters.get_mut(&target_id).unwrap();
+
+        match effect {
+            Effect::Damage(damage) => {
+                let total_damage =
+                    self.deal_damage(target_id, damage.dice.roll().total, &damage.kind);
+                Outcome::Hit {
+                    damage: total_damage,
+                    kind: damage.kind,
+                }
+            }
+            Effect::Healing(healing) => Outcome::Healed {
+                amount: target.heal(healing.dice.roll().total + healing.bonus),
+            },
+            Effect::Buff(_) => {
+                target.active_effects.push(effect);
+                target.update_armor_class();
+                Outcome::Applied
+            }
+            Effect::Condition(_) => {
+                target.active_effects.push(effect);
+                Outcome::Applied
+            }
+        }
+    }
+
+    fn use_object(&mut self, use_object: UseObject) -> ActionResult {
+        if !self.characters.contains_key(&use_object.actor_id)
+            || !self.characters.contains_key(&use_object.target_id)
+        {
+            return ActionResult::new(Outcome::Unavailable);
+        }
+        let actor = self.characters.get_mut(&use_object.actor_id).unwrap();
+
+        let Some(item) = actor.inventory.consume(&use_object.item) else {
+            return ActionResult::new(Outcome::Unavailable);
+        };
+
+        // Reading a scroll casts its spell, so it can still be counterspelled.
+        if let ItemCategory::Scroll(scroll) = item.category {
+            return self.apply_action(Action::CastSpell(CastSpell {
+                name: scroll.spell,
+                actor_id: use_object.actor_id,
+                target_id: use_object.target_id,
+                effect: scroll.effect,
+                shape: scroll.shape,
+            }));
+        }
+
+        let mut outcome = Outcome::Applied;
+        for effect in item.effects {
+            let result = self.apply_effect(use_object.target_id, effect);
+            if result != Outcome::Applied {
+                outcome = result;
+            }
+        }
+
+        ActionResult::new(outcome)
+    }
+
+    // After combat half of the spent ammunition can be found again. Thrown weapons are all
+    // picked back up.
+    pub fn recover_ammunition(&mut self) {
+        for (character_id, spent) in self.spent_ammunition.drain() {
+            let Some(character) = self.characters.get_mut(&character_id) else {
+                continue;
+            };
+
+            for mut item in spent {
+                if !matches!(item.category, ItemCategory::Weapon(_)) {
+                    item.quantity /= 2;
+                }
+                if item.quantity > 0 {
+                    character.inventory.add(item);
                 }
-                .roll()
-                .total
-                    + attacker.abilities.strength.get_modifier();
+            }
+        }
+    }
+
+    // Charged items regain their charges at dawn.
+    pub fn dawn(&mut self) {
+        for character in self.characters.values_mut() {
+            character.inventory.recharge_at_dawn();
+        }
+    }
+
+    pub fn start_rest(&mut self, kind: RestKind, party: &[u32]) -> Result<(), RestError> {
+        if self.rest.is_some() {
+            return Err(RestError::AlreadyResting);
+        }
+        if let Some(id) = party.iter().find(|id| !self.characters.contains_key(*id)) {
+            return Err(RestError::UnknownCharacter(*id));
+        }
 
-                let damage = match attack.effect {
-                    Effect::Damage(damage) => damage,
-                    _ => todo!(),
+        self.rest = Some(Rest::new(kind, party.to_vec()));
+        Ok(())
+    }
+
+    // Interrupts the rest in progress, e.g. when a wandering monster shows up.
+    pub fn interrupt_rest(&mut self) {
+        if let Some(rest) = &mut self.rest {
+            rest.interrupted = true;
+        }
+    }
+
+    // Fighting, casting a spell or taking damage interrupts a rest the character is part of.
+    fn disturb_rest(&mut self, character_id: u32) {
+        if self
+            .rest
+            .as_ref()
+            .is_some_and(|rest| rest.includes(character_id))
+        {
+            self.interrupt_rest();
+        }
+    }
+
+    // Ends the rest in progress. On a short rest the party spends the listed hit dice, one
+    // per entry, and the hit points each member regained are returned. An interrupted rest
+    // ends without any benefits and returns `RestError::Interrupted`.
+    pub fn finish_rest(&mut self, hit_dice: &[(u32, Class)]) -> Result<Vec<(u32, i32)>, RestError> {
+        let rest = self.rest.as_ref().ok_or(RestError::NotResting)?;
+        if rest.interrupted {
+            self.rest = None;
+            return Err(RestError::Interrupted);
+        }
+        if rest.kind != RestKind::Short && !hit_dice.is_empty() {
+            return Err(RestError::NotShortRest);
+        }
+
+        let mut characters = self.characters.clone();
+        let mut healed: Vec<(u32, i32)> = vec![];
+        for (id, class) in hit_dice {
+            if !rest.includes(*id) {
+                return Err(RestError::NotInParty(*id));
+            }
+            let character = characters
+                .get_mut(id)
+                .ok_or(RestError::UnknownCharacter(*id))?;
+            let amount = character.spend_hit_die(class)?;
+            match healed.iter_mut().find(|(healed_id, _)| healed_id == id) {
+                Some((_, total)) => *total += amount,
+                None => healed.push((*id, amount)),
+            }
+        }
+
+        for character in characters.values_mut().filter(|c| rest.includes(c.id)) {
+            match rest.kind {
+                RestKind::Short => character.finish_short_rest(),
+                RestKind::Long => character.finish_long_rest(),
+            }
+        }
+
+        self.characters = characters;
+        self.rest = None;
+        Ok(healed)
+    }
+
+    fn use_feature(&mut self, use_feature: UseFeature) -> ActionResult {
+        self.disturb_rest(use_feature.actor_id);
+        let actor_id = use_feature.actor_id;
+        if let FeatureAction::ChannelDivinity { target_id, .. }
+        | FeatureAction::BardicInspiration { target_id } = &use_feature.feature
+        {
+            if !self.characters.contains_key(target_id) {
+                return ActionResult::new(Outcome::Unavailable);
+            }
+        }
+        let Some(actor) = self.characters.get_mut(&actor_id) else {
+            return ActionResult::new(Outcome::Unavailable);
+        };
+
+        let outcome = match use_feature.feature {
+            FeatureAction::Rage => {
+                let Some(damage) = actor.rage_damage() else {
+                    return ActionResult::new(Outcome::Unavailable);
                 };
-                let total_damage =
-                    damage.dice.roll().total + attacker.abilities.strength.get_modifier();
+                if actor.spend_resource("Rage", 1).is_err() {
+                    return ActionResult::new(Outcome::Unavailable);
+                }
 
-                let target = self
-                    .characters
-                    .iter_mut()
-                    .find(|character| character.id == attack.target_id)
-                    .unwrap();
+                let kinds = [
+                    BuffKind::Resistance(DamageKind::Bludgeoning),
+                    BuffKind::Resistance(DamageKind::Piercing),
+                    BuffKind::Resistance(DamageKind::Slashing),
+                    BuffKind::Bonus(EnhancementBuff::DamageRoll, damage),
+                ];
+                for kind in kinds {
+                    actor.active_effects.push(Effect::Buff(Buff {
+                        kind,
+                        duration: Duration {
+                            amount: 1,
+                            unit: DurationUnit::Minutes,
+                        },
+                    }));
+                }
+                Outcome::Applied
+            }
+            FeatureAction::CreateSpellSlot(level) => match actor.create_spell_slot(level) {
+                Ok(()) => Outcome::Applied,
+                Err(_) => Outcome::Unavailable,
+            },
+            FeatureAction::ConvertSpellSlot(level) => match actor.convert_spell_slot(level) {
+                Ok(()) => Outcome::Applied,
+                Err(_) => Outcome::Unavailable,
+            },
+            FeatureAction::ChannelDivinity { target_id, effect } => {
+                if actor.spend_resource("Channel Divinity", 1).is_err() {
+                    return ActionResult::new(Outcome::Unavailable);
+                }
+                self.apply_effect(target_id, effect)
+            }
+            // Only one creature other than the bard, and only one die at a time.
+            FeatureAction::BardicInspiration { target_id } => {
+                let Some(die) = actor.inspiration_die() else {
+                    return ActionResult::new(Outcome::Unavailable);
+                };
+                let in_range = self
+                    .map
+                    .distance(actor_id, target_id)
+                    .is_none_or(|distance| distance <= BARDIC_INSPIRATION_RANGE);
+                let can_receive = target_id != actor_id
+                    && self
+                        .characters
+                        .get(&target_id)
+                        .is_some_and(|c| c.inspiration.is_none());
+                if !can_receive {
+                    return ActionResult::new(Outcome::Unavailable);
+                }
+                if !in_range {
+                    return ActionResult::new(Outcome::OutOfRange);
+                }
+                let bard = self.characters.get_mut(&actor_id).unwrap();
+                if bard.spend_resource("Bardic Inspiration", 1).is_err() {
+                    return ActionResult::new(Outcome::Unavailable);
+                }
+                let target = self.characters.get_mut(&target_id).unwrap();
+                target.inspiration = Some(die);
+                Outcome::Applied
+            }
+        };
 
-                if attack_roll < target.armor_class {
-                    println!("Attack missed!");
-                    return;
+        ActionResult::new(outcome)
+    }
+
+    fn has_hit_points(&self, character_id: u32) -> bool {
+        self.characters
+            .get(&character_id)
+            .is_some_and(|c| c.hit_points.current > 0)
+    }
+
+    fn trigger_reactions(&mut self, trigger: Trigger) -> Vec<ReactionOutcome> {
+        let candidates: Vec<Reaction> = self
+            .reactions
+            .iter()
+            .cloned()
+            .chain(self.feat_reactions())
+            .filter(|reaction| reaction.responds_to(&trigger))
+            .collect();
+
+        let mut outcomes = vec![];
+        for reaction in candidates {
+            if !self.can_react(&reaction, &trigger) {
+                continue;
+            }
+
+            self.reactions_used.insert(reaction.owner_id);
+            if reaction.is_one_shot() {
+                if let Some(index) = self.reactions.iter().position(|r| *r == reaction) {
+                    self.reactions.remove(index);
                 }
+            }
+
+            let result = self.resolve_reaction(&reaction, &trigger);
+            outcomes.push(ReactionOutcome {
+                reactor_id: reaction.owner_id,
+                kind: reaction.kind,
+                result,
+            });
+        }
+
+        outcomes
+    }
+
+    // Reactions every character with the right feat has, without registering them.
+    fn feat_reactions(&self) -> Vec<Reaction> {
+        self.characters
+            .values()
+            .flat_map(|character| {
+                character
+                    .feat_effects()
+                    .into_iter()
+                    .filter_map(move |effect| match effect {
+                        FeatEffect::Reaction(FeatReaction::DefensiveDuelist) => Some(Reaction {
+                            owner_id: character.id,
+                            kind: ReactionKind::DefensiveDuelist,
+                        }),
+                        _ => None,
+                    })
+            })
+            .collect()
+    }
+
+    fn can_react(&self, reaction: &Reaction, trigger: &Trigger) -> bool {
+        let owner_id = reaction.owner_id;
+        if !self.has_reaction(owner_id) {
+            return false;
+        }
+
+        match (&reaction.kind, trigger) {
+            (ReactionKind::OpportunityAttack(_), Trigger::LeavesReach { mover_id, .. }) => {
+                self.are_hostile(owner_id, *mover_id) && self.can_see(owner_id, *mover_id)
+            }
+            // Only worth casting when the extra 5 AC turns the hit into a miss.
+            (
+                ReactionKind::Shield,
+                Trigger::Hit {
+                    attacker_id,
+                    attack_roll,
+                    ..
+                },
+            ) => *attack_roll < self.armor_class_against(*attacker_id, owner_id) + 5,
+            (ReactionKind::UncannyDodge, Trigger::Hit { attacker_id, .. }) => {
+                self.can_see(owner_id, *attacker_id)
+            }
+            // Only against melee attacks, while wielding a finesse weapon the owner is
+            // proficient with.
+            (ReactionKind::DefensiveDuelist, Trigger::Hit { attacker_id, .. }) => {
+                let Some(owner) = self.characters.get(&owner_id) else {
+                    return false;
+                };
+                owner.equipment.main_hand.as_ref().is_some_and(|weapon| {
+                    weapon.has_property(&WeaponProperty::Finesse)
+                        && owner.is_proficient_with(weapon)
+                }) && self
+                    .map
+                    .distance(owner_id, *attacker_id)
+                    .is_none_or(|distance| distance <= 5)
+            }
+            (ReactionKind::Counterspell, Trigger::CastsSpell { caster_id }) => {
+                self.are_hostile(owner_id, *caster_id)
+                    && self.can_see(owner_id, *caster_id)
+                    && self
+                        .map
+                        .distance(owner_id, *caster_id)
+                        .is_none_or(|distance| distance <= COUNTERSPELL_RANGE)
+            }
+            (ReactionKind::Readied { .. }, _) => true,
+            _ => false,
+        }
+    }
 
-                target.hit_points.current -= total_damage;
-                if target.hit_points.current <= 0 {
-                    target.hit_points.current = 0;
+    fn resolve_reaction(&mut self, reaction: &Reaction, trigger: &Trigger) -> ActionResult {
+        match (&reaction.kind, trigger) {
+            (ReactionKind::OpportunityAttack(attack), Trigger::LeavesReach { mover_id, .. }) => {
+                self.resolve_attack(Attack {
+                    actor_id: reaction.owner_id,
+                    target_id: *mover_id,
+                    ..attack.clone()
+                })
+            }
+            (ReactionKind::Shield, _) => {
+                *self.armor_class_bonus.entry(reaction.owner_id).or_insert(0) += 5;
+                ActionResult::new(Outcome::Applied)
+            }
+            (ReactionKind::Counterspell, _) => ActionResult::new(Outcome::Countered),
+            (ReactionKind::Readied { action, .. }, _) => self.apply_action(*action.clone()),
+            _ => ActionResult::new(Outcome::Applied),
+        }
+    }
+
+    // Sneak Attack dice the attack earns: once per turn, with a finesse or ranged weapon, when
+    // the attacker has advantage or an ally stands next to the target.
+    fn sneak_attack(&self, attack: &Attack, mode: RollMode) -> Option<Dice> {
+        let weapon = &attack.weapon.as_ref()?.weapon;
+        let attacker = self.characters.get(&attack.actor_id)?;
+        let dice = attacker.sneak_attack_dice()?;
+        if self.sneak_attack_used.contains(&attack.actor_id)
+            || mode == RollMode::Disadvantage
+            || !(weapon.has_property(&WeaponProperty::Finesse) || weapon.kind == WeaponKind::Ranged)
+        {
+            return None;
+        }
+
+        let ally_adjacent = self.characters.values().any(|ally| {
+            ally.id != attack.actor_id
+                && ally.id != attack.target_id
+                && !self.are_hostile(attack.actor_id, ally.id)
+                && ally.hit_points.current > 0
+                && !ally.has_condition(ConditionKind::Incapacitated)
+                && self.map.distance(ally.id, attack.target_id) == Some(5)
+        });
+
+        (mode == RollMode::Advantage || ally_adjacent).then_some(dice)
+    }
+
+    // The Attack action: one attack, or more with Extra Attack. Stops early when the target
+    // drops or the attacker runs out of ammunition. A loading weapon only fires once per
+    // action whatever the number of attacks.
+    pub fn take_attack_action(&mut self, attack: Attack) -> Vec<ActionResult> {
+        let loading = attack
+            .weapon
+            .as_ref()
+            .is_some_and(|weapon| weapon.weapon.has_property(&WeaponProperty::Loading));
+        let attacks = match self.characters.get(&attack.actor_id) {
+            Some(character) if !loading => character.attacks_per_action(),
+            _ => 1,
+        };
+        self.repeat_attack(attack, attacks)
+    }
+
+    // Flurry of Blows: spends 1 ki for two unarmed strikes.
+    pub fn flurry_of_blows(&mut self, attack: Attack) -> Vec<ActionResult> {
+        let spent = attack.weapon.is_none()
+            && self
+                .characters
+                .get_mut(&attack.actor_id)
+                .is_some_and(|monk| monk.spend_resource("Ki", 1).is_ok());
+        if !spent {
+            return vec![ActionResult::new(Outcome::Unavailable)];
+        }
+
+        self.repeat_attack(attack, 2)
+    }
+
+    // Makes the attack up to `attacks` times, stopping early when it can't be made or the
+    // target drops.
+    fn repeat_attack(&mut self, attack: Attack, attacks: u32) -> Vec<ActionResult> {
+        let mut results = vec![];
+        for _ in 0..attacks {
+            let result = self.resolve_attack(attack.clone());
+            let stop =
+                result.outcome == Outcome::Unavailable || !self.has_hit_points(attack.target_id);
+            results.push(result);
+            if stop {
+                break;
+            }
+        }
+
+        results
+    }
+
+    fn resolve_attack(&mut self, attack: Attack) -> ActionResult {
+        if !self.characters.contains_key(&attack.actor_id)
+            || !self.characters.contains_key(&attack.target_id)
+        {
+            return ActionResult::new(Outcome::Unavailable);
+        }
+        self.disturb_rest(attack.actor_id);
+
+        let mut reactions = self.trigger_reactions(Trigger::Attacks {
+            attacker_id: attack.actor_id,
+            target_id: attack.target_id,
+        });
+
+        let mut disadvantage = false;
+        match self
+            .map
+            .check_range(attack.actor_id, attack.target_id, attack.range)
+        {
+            Some(RangeCheck::OutOfRange) => {
+                return ActionResult {
+                    outcome: Outcome::OutOfRange,
+                    reactions,
+                };
+            }
+            Some(RangeCheck::LongRange) => disadvantage = true,
+            _ => {}
+        }
+
+        if self.cover(attack.actor_id, attack.target_id) == Cover::Total {
+            return ActionResult {
+                outcome: Outcome::TotalCover,
+                reactions,
+            };
+        }
+
+        // Ammunition is only used up by an attack that actually happens.
+        if let Some(WeaponAttack { weapon, mode, .. }) = &attack.weapon {
+            let attacker = self.characters.get_mut(&attack.actor_id).unwrap();
+
+            match attacker.draw_ammunition(weapon, *mode) {
+                Ok(Some(item)) => {
+                    let spent = self.spent_ammunition.entry(attack.actor_id).or_default();
+                    match spent.iter_mut().find(|stack| stack.can_stack_with(&item)) {
+                        Some(stack) => stack.quantity += item.quantity,
+                        None => spent.push(item),
+                    }
                 }
+                Ok(None) => {}
+                Err(_) => {
+                    return ActionResult {
+                        outcome: Outcome::Unavailable,
+                        reactions,
+                    };
+                }
+            }
+        }
+
+        let attacker = self.characters.get(&attack.actor_id).unwrap();
+
+        let advantage = !self.can_see(attack.target_id, attack.actor_id);
+        disadvantage |= !self.can_see(attack.actor_id, attack.target_id);
+        disadvantage |= attacker.attack_roll_mode() == RollMode::Disadvantage;
+        // Heavy weapons are unwieldy for Small creatures.
+        disadvantage |= attacker.size() == Size::Small
+            && attack
+                .weapon
+                .as_ref()
+                .is_some_and(|weapon| weapon.weapon.has_property(&WeaponProperty::Heavy));
+        let mode = RollMode::resolve(advantage, disadvantage);
+        let sneak_attack = self.sneak_attack(&attack, mode);
+
+        let strength = attacker.abilities.strength.get_modifier();
+        let style = |style| attacker.has_fighting_style(style);
+        let (to_hit, damage_bonus) = match &attack.weapon {
+            Some(WeaponAttack {
+                weapon,
+                mode,
+                power_attack,
+            }) => {
+                let ability =
+                    weapon.ability_modifier(strength, attacker.abilities.dexterity.get_modifier());
+                let proficiency = if attacker.is_proficient_with(weapon) {
+                    attacker.proficiency_bonus()
+                } else {
+                    0
+                };
+                // Off-hand attacks don't add a positive ability modifier to damage.
+                let damage_ability = match mode {
+                    WeaponMode::OffHand if !style(FightingStyle::TwoWeaponFighting) => {
+                        ability.min(0)
+                    }
+                    _ => ability,
+                };
+                let archery = match weapon.kind {
+                    WeaponKind::Ranged if style(FightingStyle::Archery) => 2,
+                    _ => 0,
+                };
+                let dueling = match (weapon.kind, mode) {
+                    (WeaponKind::Melee, WeaponMode::OneHanded)
+                        if attacker.equipment.off_hand.is_none()
+                            && style(FightingStyle::Dueling) =>
+                    {
+                        2
+                    }
+                    _ => 0,
+                };
+                let (power_penalty, power_bonus) =
+                    if *power_attack && attacker.can_power_attack(weapon) {
+                        (POWER_ATTACK_PENALTY, POWER_ATTACK_BONUS)
+                    } else {
+                        (0, 0)
+                    };
+
+                (
+                    ability + proficiency + weapon.magic_bonus + archery - power_penalty,
+                    damage_ability + weapon.magic_bonus + dueling + power_bonus,
+                )
             }
+            None => (strength, strength),
+        };
+        let to_hit = to_hit + attacker.modifier(EnhancementBuff::AttackRoll);
+        let damage_bonus = damage_bonus + attacker.modifier(EnhancementBuff::DamageRoll);
+
+        let attack_roll = attacker.roll_d20(mode).total + to_hit;
+
+        let damage = match attack.effect {
+            Effect::Damage(damage) => damage,
+            _ => todo!(),
+        };
+        let great_weapon = matches!(
+            &attack.weapon,
+            Some(WeaponAttack {
+                weapon,
+                mode: WeaponMode::TwoHanded,
+                ..
+            }) if weapon.kind == WeaponKind::Melee
+        ) && style(FightingStyle::GreatWeaponFighting);
+        let damage_roll = if great_weapon {
+            damage.dice.roll_rerolling(2)
+        } else {
+            damage.dice.roll()
+        };
+        let mut total_damage = (damage_roll.total + damage_bonus).max(0);
+
+        if attack_roll < self.armor_class_against(attack.actor_id, attack.target_id) {
+            return ActionResult {
+                outcome: Outcome::Miss,
+                reactions,
+            };
+        }
+
+        let hit_reactions = self.trigger_reactions(Trigger::Hit {
+            attacker_id: attack.actor_id,
+            target_id: attack.target_id,
+            attack_roll,
+        });
+        let uncanny_dodge = hit_reactions
+            .iter()
+            .any(|outcome| outcome.kind == ReactionKind::UncannyDodge);
+        // Defensive Duelist adds the proficiency bonus against this attack only.
+        let duelist_bonus = if hit_reactions
+            .iter()
+            .any(|outcome| outcome.kind == ReactionKind::DefensiveDuelist)
+        {
+            self.characters
+                .get(&attack.target_id)
+                .map_or(0, |c| c.proficiency_bonus())
+        } else {
+            0
+        };
+        reactions.extend(hit_reactions);
+
+        if attack_roll < self.armor_class_against(attack.actor_id, attack.target_id) + duelist_bonus
+        {
+            return ActionResult {
+                outcome: Outcome::Miss,
+                reactions,
+            };
+        }
+
+        if let Some(dice) = sneak_attack {
+            total_damage += dice.roll().total;
+            self.sneak_attack_used.insert(attack.actor_id);
+        }
+        // Uncanny Dodge halves all of the attack's damage, Sneak Attack included.
+        if uncanny_dodge {
+            total_damage /= 2;
+        }
+
+        let total_damage = self.deal_damage(attack.target_id, total_damage, &damage.kind);
+
+        ActionResult {
+            outcome: Outcome::Hit {
+                damage: total_damage,
+                kind: damage.kind,
+            },
+            reactions,
+        }
+    }
+
+    pub fn apply_action(&mut self, action: Action) -> ActionResult {
+        match action {
+            Action::Attack(attack) => self.resolve_attack(attack),
             Action::CastSpell(spell) => {
-                let caster = self
-                    .characters
+                if !self.characters.contains_key(&spell.actor_id)
+                    || !self.characters.contains_key(&spell.target_id)
+                {
+                    return ActionResult::new(Outcome::Unavailable);
+                }
+                self.disturb_rest(spell.actor_id);
+                let reactions = self.trigger_reactions(Trigger::CastsSpell {
+                    caster_id: spell.actor_id,
+                });
+                if reactions
                     .iter()
-                    .find(|character| character.id == 1)
-                    .unwrap()
-                    .clone();
+                    .any(|outcome| outcome.result.outcome == Outcome::Countered)
+                {
+                    return ActionResult {
+                        outcome: Outcome::Countered,
+                        reactions,
+                    };
+                }
 
-                let target = self
-                    .characters
-                    .iter_mut()
-                    .find(|character| character.id == spell.target_id)
-                    .unwrap();
+                let caster = self.characters.get(&spell.actor_id).unwrap().clone();
+
+                let cover = self.cover(spell.actor_id, spell.target_id);
+                if cover == Cover::Total {
+                    return ActionResult {
+                        outcome: Outcome::TotalCover,
+                        reactions,
+                    };
+                }
+
+                let armor_class = self.armor_class_against(spell.actor_id, spell.target_id);
+                let target = self.characters.get_mut(&spell.target_id).unwrap();
 
                 match spell.effect {
                     Effect::Damage(damage) => {
-                        let spell_attack_roll = Dice {
-                            count: 1,
-                            sides: 20,
-                        }
-                        .roll()
-                        .total
-                            + caster.abilities.intelligence.get_modifier();
+                        let spell_attack_roll = caster.roll_d20(caster.attack_roll_mode()).total
+                            + caster.spell_attack_bonus();
 
-                        if spell_attack_roll < target.armor_class {
-                            println!("Spell attack missed!");
-                            return;
+                        if spell_attack_roll < armor_class {
+                            return ActionResult {
+                                outcome: Outcome::Miss,
+                                reactions,
+                            };
                         }
 
                         let total_damage = damage.dice.roll().total;
-                        let target = self
-                            .characters
-                            .iter_mut()
-                            .find(|character| character.id == spell.target_id)
-                            .unwrap();
-                        target.hit_points.current -= total_damage;
-                        if target.hit_points.current <= 0 {
-                            target.hit_points.current = 0;
-                        }
+                        let total_damage =
+                            self.deal_damage(spell.target_id, total_damage, &damage.kind);
+
+                        return ActionResult {
+                            outcome: Outcome::Hit {
+                                damage: total_damage,
+                                kind: damage.kind,
+                            },
+                            reactions,
+                        };
                     }
-                    Effect::Buff(buff) => match buff.kind {
+                    Effect::Buff(buff) => match buff.kind.clone() {
                         BuffKind::Enhancement(enhancement) => match enhancement {
                             EnhancementBuff::Strength => {
                                 target.abilities.strength.value += 2;
@@ -121,7 +1050,8 @@ impl State {
                                 target.abilities.charisma.value += 2;
                             }
                             EnhancementBuff::ArmorClass => {
-                                target.armor_class += 2;
+                                target.active_effects.push(Effect::Buff(buff));
+                                target.update_armor_class();
                             }
                             EnhancementBuff::AttackRoll => {}
                             EnhancementBuff::DamageRoll => todo!(),
@@ -130,21 +1060,38 @@ impl State {
                             EnhancementBuff::Speed => todo!(),
                             EnhancementBuff::Initiative => todo!(),
                             EnhancementBuff::HitPoints => todo!(),
-                            EnhancementBuff::HitDice => todo!(),
+                            EnhancementBuff::HitDice => {
+                                target.active_effects.push(Effect::Buff(buff));
+                            }
                             EnhancementBuff::SpellAttackRoll => todo!(),
                             EnhancementBuff::SpellSaveDC => todo!(),
                             EnhancementBuff::SpellSlots => todo!(),
                             EnhancementBuff::TemporaryHitPoints => todo!(),
                         },
+                        BuffKind::Bonus(..)
+                        | BuffKind::ArmorClassFormula(_)
+                        | BuffKind::Resistance(_) => {
+                            target.active_effects.push(Effect::Buff(buff));
+                            target.update_armor_class();
+                        }
                         BuffKind::Advantage => todo!(),
                         BuffKind::Disadvantage => todo!(),
                         BuffKind::BonusAction => todo!(),
                         BuffKind::Reaction => todo!(),
-                        BuffKind::Resistance => todo!(),
                         BuffKind::Immunity => todo!(),
                         BuffKind::Vulnerability => todo!(),
                     },
-                    Effect::Condition(_) => todo!(),
+                    effect @ (Effect::Healing(_) | Effect::Condition(_)) => {
+                        return ActionResult {
+                            outcome: self.apply_effect(spell.target_id, effect),
+                            reactions,
+                        };
+                    }
+                }
+
+                ActionResult {
+                    outcome: Outcome::Applied,
+                    reactions,
                 }
             }
             Action::Dash => todo!(),
@@ -152,9 +1099,19 @@ impl State {
             Action::Dodge => todo!(),
             Action::Help => todo!(),
             Action::Hide => todo!(),
-            Action::Ready => todo!(),
+            Action::Ready(readied) => {
+                self.register_reaction(Reaction {
+                    owner_id: readied.actor_id,
+                    kind: ReactionKind::Readied {
+                        trigger: readied.trigger,
+                        action: readied.action,
+                    },
+                });
+                ActionResult::new(Outcome::Readied)
+            }
             Action::Search => todo!(),
-            Action::UseObject => todo!(),
+            Action::UseObject(use_object) => self.use_object(use_object),
+            Action::UseFeature(use_feature) => self.use_feature(use_feature),
         }
     }
 }
@@ -163,12 +1120,16 @@ pub struct TerminalInterface {
     pub state: State,
 }
 
+impl Default for TerminalInterface {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TerminalInterface {
     pub fn new() -> Self {
         Self {
-            state: State {
-                characters: Vec::new(),
-            },
+            state: State::new(),
         }
     }
 
@@ -177,7 +1138,13 @@ impl TerminalInterface {
             println!("Choose an option:");
             println!("1. Create a new character");
             println!("2. List all characters");
-            println!("3. Exit");
+            println!("3. Level up a character");
+            println!("4. Rest the party");
+            println!("5. Save a character");
+            println!("6. Load a character");
+            println!("7. Save the session");
+            println!("8. Load a session");
+            println!("9. Exit");
             print!("Enter your choice: ");
             io::stdout().flush().unwrap();
 
@@ -187,439 +1154,469 @@ impl TerminalInterface {
             match choice {
                 1 => self.add_character(),
                 2 => self.list_characters(),
-                3 => break,
+                3 => self.level_up_character(),
+                4 => self.rest_party(),
+                5 => self.save_character(),
+                6 => self.load_character(),
+                7 => self.save_session(),
+                8 => self.load_session(),
+                9 => break,
                 _ => println!("Invalid choice"),
             }
         }
     }
 
-    fn add_character(&mut self) {
-        let mut name_input = String::new();
-        print!("Enter character name: ");
+    fn prompt(message: &str) -> String {
+        let mut input = String::new();
+        print!("{}", message);
         io::stdout().flush().unwrap();
-        io::stdin().read_line(&mut name_input).unwrap();
-        let name = name_input.trim().to_string();
+        io::stdin().read_line(&mut input).unwrap();
+        input.trim().to_string()
+    }
 
-        let mut level_input = String::new();
-        print!("Enter a character level: ");
-        io::stdout().flush().unwrap();
-        io::stdin().read_line(&mut level_input).unwrap();
-        let level = level_input.trim().parse::<u32>().unwrap();
+    // Asks which of the given scores goes to each ability, using every score once.
+    fn assign_scores(mut pool: Vec<u32>) -> [u32; 6] {
+        let mut scores = [0; 6];
+        for (score, ability) in scores.iter_mut().zip(ABILITY_NAMES) {
+            loop {
+                let input = Self::prompt(&format!("Assign a score to {} {:?}: ", ability, pool));
+                match input
+                    .parse::<u32>()
+                    .ok()
+                    .and_then(|value| pool.iter().position(|s| *s == value))
+                {
+                    Some(index) => {
+                        *score = pool.remove(index);
+                        break;
+                    }
+                    None => println!("Pick one of the remaining scores"),
+                }
+            }
+        }
+        scores
+    }
+
+    fn choose_skills(count: usize, options: &[SkillType]) -> Vec<SkillType> {
+        if count == 0 {
+            return vec![];
+        }
+
+        let input = Self::prompt(&format!(
+            "Choose {} skills, separated by commas {:?}: ",
+            count, options
+        ));
+        input
+            .split(',')
+            .filter_map(|name| {
+                options
+                    .iter()
+                    .find(|skill| format!("{:?}", skill) == name.trim())
+                    .cloned()
+            })
+            .collect()
+    }
+
+    // Prompts for the choices the feat needs, if any.
+    fn choose_feat_options(name: &str) -> FeatChoices {
+        let mut choices = FeatChoices::default();
+        for effect in feat(name).map_or(&[][..], |feat| &feat.effects) {
+            match effect {
+                FeatEffect::AbilityChoice(options) => {
+                    let input =
+                        Self::prompt(&format!("Choose an ability to increase {:?}: ", options));
+                    choices.ability = options
+                        .iter()
+                        .find(|ability| format!("{:?}", ability) == input)
+                        .cloned();
+                }
+                FeatEffect::SkillChoices(count) => {
+                    choices.skills = Self::choose_skills(*count, &SkillType::ALL);
+                }
+                _ => {}
+            }
+        }
+        choices
+    }
+
+    fn buy_scores() -> [u32; 6] {
+        let mut scores = [8; 6];
+        for (score, ability) in scores.iter_mut().zip(ABILITY_NAMES) {
+            *score = Self::prompt(&format!("Enter a value for {} (8-15): ", ability))
+                .parse::<u32>()
+                .unwrap_or(0);
+        }
+        scores
+    }
 
-        let mut hp_input = String::new();
-        print!("Enter a character hitpoints: ");
-        io::stdout().flush().unwrap();
-        io::stdin().read_line(&mut hp_input).unwrap();
-        let hp = hp_input.trim().parse::<i32>().unwrap();
+    fn add_character(&mut self) {
+        let name = Self::prompt("Enter character name: ");
+        let level = Self::prompt("Enter a character level: ")
+            .parse::<u32>()
+            .unwrap_or(0);
 
-        let mut race_input = String::new();
-        print!("Enter a character race: (Human, Elf, Dwarf, HalfOrc, Halfling)");
-        io::stdout().flush().unwrap();
-        io::stdin().read_line(&mut race_input).unwrap();
-        let race = match race_input.trim().to_string().as_str() {
+        let race = match Self::prompt(
+            "Enter a character race: (Human, Elf, Dwarf, Halfling, Gnome, HalfElf, HalfOrc, Tiefling)",
+        )
+        .as_str()
+        {
             "Human" => Race::Human,
             "Elf" => Race::Elf,
             "Dwarf" => Race::Dwarf,
-            "HalfOrc" => Race::HalfOrc,
             "Halfling" => Race::Halfling,
+            "Gnome" => Race::Gnome,
+            "HalfElf" => Race::HalfElf,
+            "HalfOrc" => Race::HalfOrc,
+            "Tiefling" => Race::Tiefling,
+            _ => {
+                println!("Invalid race");
+                return;
+            }
+        };
 
-            _ => todo!(),
+        let class_names: Vec<String> = classes()
+            .iter()
+            .map(|definition| format!("{:?}", definition.class))
+            .collect();
+        let input = Self::prompt(&format!(
+            "Enter a character class: ({})",
+            class_names.join(", ")
+        ));
+        let Some(class) = classes()
+            .iter()
+            .find(|definition| format!("{:?}", definition.class) == input)
+        else {
+            println!("Invalid class");
+            return;
         };
 
-        let mut armor_class_input = String::new();
-        print!("Enter a character armor class: ");
-        io::stdout().flush().unwrap();
-        io::stdin().read_line(&mut armor_class_input).unwrap();
-        let armor_class = armor_class_input.trim().parse::<i32>().unwrap();
+        println!("Choose how to generate ability scores:");
+        println!("1. Point buy ({} points)", POINT_BUY_BUDGET);
+        println!("2. Standard array {:?}", STANDARD_ARRAY);
+        println!("3. Roll 4d6 and drop the lowest");
+        let ability_scores = match Self::prompt("Enter your choice: ").as_str() {
+            "1" => AbilityScores::PointBuy(Self::buy_scores()),
+            "2" => AbilityScores::StandardArray(Self::assign_scores(STANDARD_ARRAY.to_vec())),
+            "3" => {
+                let rolled = roll_ability_scores();
+                println!("You rolled {:?}", rolled);
+                AbilityScores::Rolled(Self::assign_scores(rolled.to_vec()))
+            }
+            _ => {
+                println!("Invalid choice");
+                return;
+            }
+        };
 
-        let mut class_input = String::new();
-        print!("Enter a character class: (Fighter, Wizard, Rogue, Cleric)");
-        io::stdout().flush().unwrap();
-        io::stdin().read_line(&mut class_input).unwrap();
-        let class_type = match class_input.trim().to_string().as_str() {
-            "Fighter" => Class::Fighter,
-            "Wizard" => Class::Wizard,
-            "Rogue" => Class::Rogue,
-            "Cleric" => Class::Cleric,
+        let definition = race_definition(&race);
+        let mut builder = CharacterBuilder::new(&name).with_race(race);
+        if !definition.subraces.is_empty() {
+            let names: Vec<&str> = definition
+                .subraces
+                .iter()
+                .map(|subrace| subrace.name.as_str())
+                .collect();
+            builder = builder.with_subrace(&Self::prompt(&format!(
+                "Enter a subrace: ({})",
+                names.join(", ")
+            )));
+        }
+        if definition.ability_choices > 0 {
+            let mut choices = vec![];
+            for _ in 0..definition.ability_choices {
+                let input = Self::prompt("Enter an ability to increase by 1: ");
+                if let Some(index) = ABILITY_NAMES.iter().position(|name| *name == input) {
+                    choices.push(SavingThrow::ALL[index].clone());
+                }
+            }
+            builder = builder.with_ability_choices(choices);
+        }
 
-            _ => todo!(),
+        let mut builder = builder
+            .with_class(class.class.clone())
+            .with_level(level)
+            .with_ability_scores(ability_scores);
+
+        builder = builder.with_class_skills(Self::choose_skills(
+            class.skill_choices,
+            &class.skill_options,
+        ));
+        for skill in Self::choose_skills(definition.skill_choices, &SkillType::ALL) {
+            builder = builder.with_skill_proficiency(skill, Proficiency::Proficient);
+        }
+
+        let names: Vec<&str> = backgrounds()
+            .iter()
+            .map(|background| background.name.as_str())
+            .collect();
+        let input = Self::prompt(&format!("Enter a background: ({})", names.join(", ")));
+        if let Some(background) = background(&input) {
+            builder = builder.with_background(&background.name);
+            if background.language_choices > 0 {
+                let input = Self::prompt(&format!(
+                    "Choose {} languages, separated by commas: ",
+                    background.language_choices
+                ));
+                builder = builder
+                    .with_languages(input.split(',').map(|s| s.trim().to_string()).collect());
+            }
+        }
+
+        let mut features: Vec<&ClassFeature> = class.features.iter().collect();
+        if level >= class.subclass_level {
+            let names: Vec<&str> = class
+                .subclasses
+                .iter()
+                .map(|subclass| subclass.name.as_str())
+                .collect();
+            let input = Self::prompt(&format!("Enter a subclass: ({})", names.join(", ")));
+            if let Some(subclass) = class.subclass(&input) {
+                features.extend(&subclass.features);
+            }
+            builder = builder.with_subclass(&input);
+        }
+        let style_choices = features
+            .iter()
+            .filter(|feature| feature.level <= level)
+            .flat_map(|feature| &feature.effects)
+            .filter(|effect| **effect == FeatureEffect::FightingStyle)
+            .count();
+        for _ in 0..style_choices {
+            let input = Self::prompt(&format!(
+                "Enter a fighting style: ({:?})",
+                FightingStyle::ALL
+            ));
+            if let Some(style) = FightingStyle::ALL
+                .into_iter()
+                .find(|style| format!("{:?}", style) == input)
+            {
+                builder = builder.with_fighting_style(style);
+            }
+        }
+
+        let id = self.state.allocate_id();
+        match builder.build(id) {
+            Ok(character) => self.state.add_character(character).unwrap(),
+            Err(error) => println!("Could not create character: {:?}", error),
+        }
+    }
+
+    // Awards experience or a milestone, then asks for whatever choices the new level needs.
+    fn level_up_character(&mut self) {
+        let name = Self::prompt("Enter the character's name: ");
+        let Some(character) = self.state.characters.values_mut().find(|c| c.name == name) else {
+            println!("No character named {}", name);
+            return;
         };
-        let class = ClassDetails {
-            name: class_input.trim().to_string(),
-            hit_dice: {
-                match class_type {
-                    Class::Fighter => 10,
-                    Class::Wizard => 6,
-                    Class::Rogue => 8,
-                    Class::Cleric => 8,
-                    Class::Bard => todo!(),
-                    Class::Druid => todo!(),
-                    Class::Monk => todo!(),
-                    Class::Paladin => todo!(),
-                    Class::Ranger => todo!(),
-                    Class::Sorcerer => todo!(),
-                    Class::Warlock => todo!(),
-                    Class::Barbarian => todo!(),
-                }
-            },
-            saving_throws: {
-                match class_type {
-                    Class::Fighter => vec![SavingThrow::Strength, SavingThrow::Constitution],
-                    Class::Wizard => vec![SavingThrow::Intelligence, SavingThrow::Wisdom],
-                    Class::Rogue => vec![SavingThrow::Dexterity, SavingThrow::Intelligence],
-                    Class::Cleric => vec![SavingThrow::Wisdom, SavingThrow::Charisma],
-                    Class::Bard => todo!(),
-                    Class::Druid => todo!(),
-                    Class::Monk => todo!(),
-                    Class::Paladin => todo!(),
-                    Class::Ranger => todo!(),
-                    Class::Sorcerer => todo!(),
-                    Class::Warlock => todo!(),
-                    Class::Barbarian => todo!(),
+
+        match character.advancement {
+            Advancement::Experience(_) => {
+                let experience = Self::prompt("Enter experience earned: ")
+                    .parse::<u32>()
+                    .unwrap_or(0);
+                character.gain_experience(experience);
+            }
+            Advancement::Milestone { .. } => {
+                if Self::prompt("Reached a milestone? (y/n) ") == "y" {
+                    character.reach_milestone();
                 }
-            },
-        };
+            }
+        }
 
-        let mut abilities = Abilities {
-            strength: Ability { value: 0 },
-            dexterity: Ability { value: 0 },
-            constitution: Ability { value: 0 },
-            intelligence: Ability { value: 0 },
-            wisdom: Ability { value: 0 },
-            charisma: Ability { value: 0 },
-        };
-        let abilities_list = vec![
-            "strength",
-            "dexterity",
-            "constitution",
-            "intelligence",
-            "wisdom",
-            "charisma",
-        ];
-        for ability in abilities_list {
-            let mut input = String::new();
-            print!("Enter a value for {}: ", ability);
-            io::stdout().flush().unwrap();
-            io::stdin().read_line(&mut input).unwrap();
-            let value = input.trim().parse::<u32>().unwrap();
-            match ability {
-                "strength" => abilities.strength.value = value,
-                "dexterity" => abilities.dexterity.value = value,
-                "constitution" => abilities.constitution.value = value,
-                "intelligence" => abilities.intelligence.value = value,
-                "wisdom" => abilities.wisdom.value = value,
-                "charisma" => abilities.charisma.value = value,
-                _ => println!("Invalid ability"),
-            }
-        }
-
-        let skill_list = vec![
-            "acrobatics",
-            "animal_handling",
-            "arcana",
-            "athletics",
-            "deception",
-            "history",
-            "insight",
-            "intimidation",
-            "investigation",
-            "medicine",
-            "nature",
-            "perception",
-            "performance",
-            "persuasion",
-            "religion",
-            "sleight_of_hand",
-            "stealth",
-            "survival",
-        ];
-        let mut skills = Skills {
-            acrobatics: Skill {
-                value: 0,
-                kind: SkillType::Acrobatics,
-            },
-            animal_handling: Skill {
-                value: 0,
-                kind: SkillType::AnimalHandling,
-            },
-            arcana: Skill {
-                value: 0,
-                kind: SkillType::Arcana,
-            },
-            athletics: Skill {
-                value: 0,
-                kind: SkillType::Athletics,
-            },
-            deception: Skill {
-                value: 0,
-                kind: SkillType::Deception,
-            },
-            history: Skill {
-                value: 0,
-                kind: SkillType::History,
-            },
-            insight: Skill {
-                value: 0,
-                kind: SkillType::Insight,
-            },
-            intimidation: Skill {
-                value: 0,
-                kind: SkillType::Intimidation,
-            },
-            investigation: Skill {
-                value: 0,
-                kind: SkillType::Investigation,
-            },
-            medicine: Skill {
-                value: 0,
-                kind: SkillType::Medicine,
-            },
-            nature: Skill {
-                value: 0,
-                kind: SkillType::Nature,
-            },
-            perception: Skill {
-                value: 0,
-                kind: SkillType::Perception,
-            },
-            performance: Skill {
-                value: 0,
-                kind: SkillType::Performance,
-            },
-            persuasion: Skill {
-                value: 0,
-                kind: SkillType::Persuasion,
-            },
-            religion: Skill {
-                value: 0,
-                kind: SkillType::Religion,
-            },
-            sleight_of_hand: Skill {
-                value: 0,
-                kind: SkillType::SleightOfHand,
-            },
-            stealth: Skill {
-                value: 0,
-                kind: SkillType::Stealth,
-            },
-            survival: Skill {
-                value: 0,
-                kind: SkillType::Survival,
-            },
+        let input = Self::prompt("Enter the class to take a level in: ");
+        let Some(class) = classes()
+            .iter()
+            .find(|definition| format!("{:?}", definition.class) == input)
+        else {
+            println!("Invalid class");
+            return;
         };
-        for skill in skill_list {
-            let mut input = String::new();
-            print!("Enter a value for {}: ", skill);
-            io::stdout().flush().unwrap();
-            io::stdin().read_line(&mut input).unwrap();
-            let value = input.trim().parse::<u32>().unwrap();
-            match skill {
-                "acrobatics" => skills.acrobatics.value = value,
-                "animal_handling" => skills.animal_handling.value = value,
-                "arcana" => skills.arcana.value = value,
-                "athletics" => skills.athletics.value = value,
-                "deception" => skills.deception.value = value,
-                "history" => skills.history.value = value,
-                "insight" => skills.insight.value = value,
-                "intimidation" => skills.intimidation.value = value,
-                "investigation" => skills.investigation.value = value,
-                "medicine" => skills.medicine.value = value,
-                "nature" => skills.nature.value = value,
-                "perception" => skills.perception.value = value,
-                "performance" => skills.performance.value = value,
-                "persuasion" => skills.persuasion.value = value,
-                "religion" => skills.religion.value = value,
-                "sleight_of_hand" => skills.sleight_of_hand.value = value,
-                "stealth" => skills.stealth.value = value,
-                "survival" => skills.survival.value = value,
-                _ => println!("Invalid skill"),
-            }
-        }
-
-        let skill_proficiencies_list = vec![
-            "acrobatics",
-            "animal_handling",
-            "arcana",
-            "athletics",
-            "deception",
-            "history",
-            "insight",
-            "intimidation",
-            "investigation",
-            "medicine",
-            "nature",
-            "perception",
-            "performance",
-            "persuasion",
-            "religion",
-            "sleight_of_hand",
-            "stealth",
-            "survival",
-        ];
-        let mut skill_proficiencies = SkillProficiencies {
-            acrobatics: Proficiency::NotProficient,
-            animal_handling: Proficiency::NotProficient,
-            arcana: Proficiency::NotProficient,
-            athletics: Proficiency::NotProficient,
-            deception: Proficiency::NotProficient,
-            history: Proficiency::NotProficient,
-            insight: Proficiency::NotProficient,
-            intimidation: Proficiency::NotProficient,
-            investigation: Proficiency::NotProficient,
-            medicine: Proficiency::NotProficient,
-            nature: Proficiency::NotProficient,
-            perception: Proficiency::NotProficient,
-            performance: Proficiency::NotProficient,
-            persuasion: Proficiency::NotProficient,
-            religion: Proficiency::NotProficient,
-            sleight_of_hand: Proficiency::NotProficient,
-            stealth: Proficiency::NotProficient,
-            survival: Proficiency::NotProficient,
+        let hit_points = match Self::prompt("Roll for hit points? (y/n) ").as_str() {
+            "y" => HitPointIncrease::Roll,
+            _ => HitPointIncrease::Average,
         };
-        for skill_proficiency in skill_proficiencies_list {
-            let mut input = String::new();
-            print!("Is {} proficient? (y/n/h/e): ", skill_proficiency);
-            io::stdout().flush().unwrap();
-            io::stdin().read_line(&mut input).unwrap();
-            let value = input.trim().to_string();
-            match value.as_str() {
-                "y" => match skill_proficiency {
-                    "acrobatics" => skill_proficiencies.acrobatics = Proficiency::Proficient,
-                    "animal_handling" => {
-                        skill_proficiencies.animal_handling = Proficiency::Proficient
-                    }
-                    "arcana" => skill_proficiencies.arcana = Proficiency::Proficient,
-                    "athletics" => skill_proficiencies.athletics = Proficiency::Proficient,
-                    "deception" => skill_proficiencies.deception = Proficiency::Proficient,
-                    "history" => skill_proficiencies.history = Proficiency::Proficient,
-                    "insight" => skill_proficiencies.insight = Proficiency::Proficient,
-                    "intimidation" => skill_proficiencies.intimidation = Proficiency::Proficient,
-                    "investigation" => skill_proficiencies.investigation = Proficiency::Proficient,
-                    "medicine" => skill_proficiencies.medicine = Proficiency::Proficient,
-                    "nature" => skill_proficiencies.nature = Proficiency::Proficient,
-                    "perception" => skill_proficiencies.perception = Proficiency::Proficient,
-                    "performance" => skill_proficiencies.performance = Proficiency::Proficient,
-                    "persuasion" => skill_proficiencies.persuasion = Proficiency::Proficient,
-                    "religion" => skill_proficiencies.religion = Proficiency::Proficient,
-                    "sleight_of_hand" => {
-                        skill_proficiencies.sleight_of_hand = Proficiency::Proficient
-                    }
-                    "stealth" => skill_proficiencies.stealth = Proficiency::Proficient,
-                    "survival" => skill_proficiencies.survival = Proficiency::Proficient,
-                    _ => println!("Invalid skill"),
-                },
 
-                "n" => match skill_proficiency {
-                    "acrobatics" => skill_proficiencies.acrobatics = Proficiency::NotProficient,
-                    "animal_handling" => {
-                        skill_proficiencies.animal_handling = Proficiency::NotProficient
-                    }
-                    "arcana" => skill_proficiencies.arcana = Proficiency::NotProficient,
-                    "athletics" => skill_proficiencies.athletics = Proficiency::NotProficient,
-                    "deception" => skill_proficiencies.deception = Proficiency::NotProficient,
-                    "history" => skill_proficiencies.history = Proficiency::NotProficient,
-                    "insight" => skill_proficiencies.insight = Proficiency::NotProficient,
-                    "intimidation" => skill_proficiencies.intimidation = Proficiency::NotProficient,
-                    "investigation" => {
-                        skill_proficiencies.investigation = Proficiency::NotProficient
-                    }
-                    "medicine" => skill_proficiencies.medicine = Proficiency::NotProficient,
-                    "nature" => skill_proficiencies.nature = Proficiency::NotProficient,
-                    "perception" => skill_proficiencies.perception = Proficiency::NotProficient,
-                    "performance" => skill_proficiencies.performance = Proficiency::NotProficient,
-                    "persuasion" => skill_proficiencies.persuasion = Proficiency::NotProficient,
-                    "religion" => skill_proficiencies.religion = Proficiency::NotProficient,
-                    "sleight_of_hand" => {
-                        skill_proficiencies.sleight_of_hand = Proficiency::NotProficient
+        let mut level_up = LevelUp::new(class.class.clone()).with_hit_points(hit_points);
+        let mut last_error = None;
+        loop {
+            let error = match level_up.clone().apply(character) {
+                Ok(features) => {
+                    println!("{} reached level {}", character.name, character.level());
+                    for feature in features {
+                        println!("New feature: {}", feature.name);
                     }
-                    "stealth" => skill_proficiencies.stealth = Proficiency::NotProficient,
-                    "survival" => skill_proficiencies.survival = Proficiency::NotProficient,
-                    _ => println!("Invalid skill"),
-                },
+                    return;
+                }
+                Err(error) if last_error.as_ref() == Some(&error) => {
+                    println!("Could not level up: {:?}", error);
+                    return;
+                }
+                Err(error) => error,
+            };
 
-                "h" => match skill_proficiency {
-                    "acrobatics" => skill_proficiencies.acrobatics = Proficiency::HalfProficient,
-                    "animal_handling" => {
-                        skill_proficiencies.animal_handling = Proficiency::HalfProficient
-                    }
-                    "arcana" => skill_proficiencies.arcana = Proficiency::HalfProficient,
-                    "athletics" => skill_proficiencies.athletics = Proficiency::HalfProficient,
-                    "deception" => skill_proficiencies.deception = Proficiency::HalfProficient,
-                    "history" => skill_proficiencies.history = Proficiency::HalfProficient,
-                    "insight" => skill_proficiencies.insight = Proficiency::HalfProficient,
-                    "intimidation" => {
-                        skill_proficiencies.intimidation = Proficiency::HalfProficient
-                    }
-                    "investigation" => {
-                        skill_proficiencies.investigation = Proficiency::HalfProficient
+            level_up = match &error {
+                LevelUpError::Multiclass(MulticlassError::InvalidSkillChoices) => level_up
+                    .with_skills(Self::choose_skills(
+                        class.multiclassing.skill_choices,
+                        &class.skill_options,
+                    )),
+                LevelUpError::MissingSubclass => {
+                    let names: Vec<&str> = class
+                        .subclasses
+                        .iter()
+                        .map(|subclass| subclass.name.as_str())
+                        .collect();
+                    level_up.with_subclass(&Self::prompt(&format!(
+                        "Enter a subclass: ({})",
+                        names.join(", ")
+                    )))
+                }
+                LevelUpError::MissingFightingStyle => {
+                    let input = Self::prompt(&format!(
+                        "Enter a fighting style: ({:?})",
+                        FightingStyle::ALL
+                    ));
+                    match FightingStyle::ALL
+                        .into_iter()
+                        .find(|style| format!("{:?}", style) == input)
+                    {
+                        Some(style) => level_up.with_fighting_style(style),
+                        None => level_up,
                     }
-                    "medicine" => skill_proficiencies.medicine = Proficiency::HalfProficient,
-                    "nature" => skill_proficiencies.nature = Proficiency::HalfProficient,
-                    "perception" => skill_proficiencies.perception = Proficiency::HalfProficient,
-                    "performance" => skill_proficiencies.performance = Proficiency::HalfProficient,
-                    "persuasion" => skill_proficiencies.persuasion = Proficiency::HalfProficient,
-                    "religion" => skill_proficiencies.religion = Proficiency::HalfProficient,
-                    "sleight_of_hand" => {
-                        skill_proficiencies.sleight_of_hand = Proficiency::HalfProficient
+                }
+                LevelUpError::MissingImprovement => {
+                    let input = Self::prompt(
+                        "Enter two abilities to increase separated by commas, or a feat: ",
+                    );
+                    let abilities: Vec<SavingThrow> = input
+                        .split(',')
+                        .filter_map(|name| {
+                            let index = ABILITY_NAMES.iter().position(|n| *n == name.trim())?;
+                            Some(SavingThrow::ALL[index].clone())
+                        })
+                        .collect();
+                    match &abilities[..] {
+                        [first, second] => level_up.with_improvement(
+                            AbilityScoreImprovement::Increase(first.clone(), second.clone()),
+                        ),
+                        _ => level_up.with_improvement(AbilityScoreImprovement::Feat(
+                            input.clone(),
+                            Self::choose_feat_options(&input),
+                        )),
                     }
-                    "stealth" => skill_proficiencies.stealth = Proficiency::HalfProficient,
-                    "survival" => skill_proficiencies.survival = Proficiency::HalfProficient,
-                    _ => println!("Invalid skill"),
-                },
+                }
+                LevelUpError::InvalidSpellChoices => {
+                    let input = Self::prompt("Enter the new spells, separated by commas: ");
+                    level_up.with_spells(input.split(',').map(|s| s.trim().to_string()).collect())
+                }
+                _ => {
+                    println!("Could not level up: {:?}", error);
+                    return;
+                }
+            };
+            last_error = Some(error);
+        }
+    }
 
-                "e" => match skill_proficiency {
-                    "acrobatics" => skill_proficiencies.acrobatics = Proficiency::Expertise,
-                    "animal_handling" => {
-                        skill_proficiencies.animal_handling = Proficiency::Expertise
-                    }
-                    "arcana" => skill_proficiencies.arcana = Proficiency::Expertise,
-                    "athletics" => skill_proficiencies.athletics = Proficiency::Expertise,
-                    "deception" => skill_proficiencies.deception = Proficiency::Expertise,
-                    "history" => skill_proficiencies.history = Proficiency::Expertise,
-                    "insight" => skill_proficiencies.insight = Proficiency::Expertise,
-                    "intimidation" => skill_proficiencies.intimidation = Proficiency::Expertise,
-                    "investigation" => skill_proficiencies.investigation = Proficiency::Expertise,
-                    "medicine" => skill_proficiencies.medicine = Proficiency::Expertise,
-                    "nature" => skill_proficiencies.nature = Proficiency::Expertise,
-                    "perception" => skill_proficiencies.perception = Proficiency::Expertise,
-                    "performance" => skill_proficiencies.performance = Proficiency::Expertise,
-                    "persuasion" => skill_proficiencies.persuasion = Proficiency::Expertise,
-                    "religion" => skill_proficiencies.religion = Proficiency::Expertise,
-                    "sleight_of_hand" => {
-                        skill_proficiencies.sleight_of_hand = Proficiency::Expertise
-                    }
-                    "stealth" => skill_proficiencies.stealth = Proficiency::Expertise,
-                    "survival" => skill_proficiencies.survival = Proficiency::Expertise,
-                    _ => println!("Invalid skill"),
-                },
+    fn rest_party(&mut self) {
+        let kind = match Self::prompt("Take a short or a long rest? (short/long) ").as_str() {
+            "short" => RestKind::Short,
+            "long" => RestKind::Long,
+            _ => {
+                println!("Invalid rest");
+                return;
+            }
+        };
+        let party: Vec<u32> = self.state.characters.keys().copied().collect();
+        if let Err(error) = self.state.start_rest(kind, &party) {
+            println!("Could not rest: {:?}", error);
+            return;
+        }
 
-                _ => println!("Invalid skill"),
+        // Hit dice are spent from the classes in the order they were taken.
+        let mut hit_dice = vec![];
+        if kind == RestKind::Short {
+            for character in self.state.characters.values() {
+                let count = Self::prompt(&format!(
+                    "How many hit dice does {} spend? {:?} ",
+                    character.name,
+                    character.remaining_hit_dice()
+                ))
+                .parse::<i32>()
+                .unwrap_or(0);
+                let classes = character.classes.iter().flat_map(|class| {
+                    let remaining = (class.level - class.hit_dice_spent) as usize;
+                    std::iter::repeat_n(class.kind.clone(), remaining)
+                });
+                hit_dice.extend(
+                    classes
+                        .take(count.max(0) as usize)
+                        .map(|class| (character.id, class)),
+                );
             }
         }
 
-        let character = Character {
-            id: 1,
-            name,
-            race,
-            level,
-            class,
-            abilities,
-            skills,
-            armor_class,
-            proficiencies: skill_proficiencies,
-            hit_points: HitPoints {
-                current: hp,
-                max: hp,
-                temporary: 0,
-            },
-            active_effects: Vec::new(),
-            inventory: Inventory { items: Vec::new() },
+        match self.state.finish_rest(&hit_dice) {
+            Ok(healed) => {
+                for (id, amount) in healed {
+                    let character = self.state.characters.get(&id).unwrap();
+                    println!("{} regained {} hit points", character.name, amount);
+                }
+            }
+            Err(error) => {
+                println!("Could not rest: {:?}", error);
+                self.state.rest = None;
+            }
+        }
+    }
+
+    fn save_character(&self) {
+        let name = Self::prompt("Enter character name: ");
+        let Some(character) = self.state.characters().find(|c| c.name == name) else {
+            println!("No character named {}", name);
+            return;
         };
-        self.state.characters.push(character);
+        let path = Self::prompt("Enter a file to save to: ");
+        match save_character(character, Path::new(&path)) {
+            Ok(()) => println!("Saved {} to {}", character.name, path),
+            Err(error) => println!("Could not save character: {:?}", error),
+        }
+    }
+
+    fn load_character(&mut self) {
+        let path = Self::prompt("Enter a file to load from: ");
+        match load_character(Path::new(&path)) {
+            // The character gets a new id so it can't clash with one already in the session.
+            Ok(mut character) => {
+                println!("Loaded {}", character.name);
+                character.id = self.state.allocate_id();
+                self.state.add_character(character).unwrap();
+            }
+            Err(error) => println!("Could not load character: {:?}", error),
+        }
+    }
+
+    fn save_session(&self) {
+        let path = Self::prompt("Enter a file to save to: ");
+        match save_session(&self.state, Path::new(&path)) {
+            Ok(()) => println!("Saved the session to {}", path),
+            Err(error) => println!("Could not save session: {:?}", error),
+        }
+    }
+
+    // Replaces the current session with the saved one.
+    fn load_session(&mut self) {
+        let path = Self::prompt("Enter a file to load from: ");
+        match load_session(Path::new(&path)) {
+            Ok(state) => {
+                self.state = state;
+                println!("Loaded {} characters", self.state.characters.len());
+            }
+            Err(error) => println!("Could not load session: {:?}", error),
+        }
     }
 
     fn list_characters(&self) {
-        for character in &self.state.characters {
+        for character in self.state.characters() {
             println!("{:#?}", character);
         }
     }
@@ -627,17 +1624,33 @@ impl TerminalInterface {
 
 #[cfg(test)]
 mod tests {
-    use crate::{actions::Attack, effect::Damage, effect::DamageKind, models::Dice};
+    use crate::{
+        actions::{Attack, CastSpell, Shape},
+        classes::SpellSlotError,
+        currency::Purse,
+        effect::Damage,
+        effect::DamageKind,
+        effect::Healing,
+        items::Equipment,
+        items::{
+            EquipmentSlot, Weapon, WeaponCategory, WeaponKind, WeaponProficiency, WeaponProperty,
+            WondrousItem,
+        },
+        map::{AttackRange, Terrain},
+        models::{
+            seed_rng, Abilities, Ability, Class, ClassDetails, Dice, HitPoints, Inventory, Item,
+            Skill, SkillProficiencies, Skills,
+        },
+        reactions::ReadyTrigger,
+        resources::{ResourceError, UNLIMITED},
+    };
 
     use super::*;
 
-    #[test]
-    fn test_apply_action() {
-        let mut state = State::new();
-
-        let attacker = Character {
-            id: 1,
-            name: String::from("Attacker"),
+    fn test_character(id: u32, name: &str) -> Character {
+        Character {
+            id,
+            name: String::from(name),
             active_effects: Vec::new(),
             abilities: Abilities {
                 strength: Ability { value: 10 },
@@ -648,12 +1661,24 @@ mod tests {
                 charisma: Ability { value: 10 },
             },
             race: Race::Human,
-            class: ClassDetails {
+            subrace: None,
+            background: None,
+            classes: vec![ClassDetails {
+                kind: Class::Fighter,
                 name: String::from("Fighter"),
+                level: 1,
                 hit_dice: 10,
+                hit_dice_spent: 0,
                 saving_throws: vec![SavingThrow::Strength, SavingThrow::Constitution],
-            },
-            level: 1,
+                subclass: None,
+                fighting_styles: vec![],
+                spells_known: vec![],
+            }],
+            advancement: Advancement::Experience(0),
+            feats: vec![],
+            expended_spell_slots: [0; 9],
+            resources_spent: HashMap::new(),
+            inspiration: None,
             skills: Skills {
                 acrobatics: Skill {
                     kind: SkillType::Acrobatics,
@@ -748,21 +1773,42 @@ mod tests {
                 stealth: Proficiency::NotProficient,
                 survival: Proficiency::NotProficient,
             },
-            inventory: Inventory { items: Vec::new() },
+            weapon_proficiencies: vec![],
+            armor_proficiencies: vec![],
+            tool_proficiencies: vec![],
+            saving_throw_proficiencies: vec![SavingThrow::Strength, SavingThrow::Constitution],
+            inventory: Inventory {
+                items: Vec::new(),
+                purse: Purse::default(),
+            },
+            equipment: Equipment::default(),
             hit_points: HitPoints {
                 current: 10,
                 max: 10,
                 temporary: 10,
             },
             armor_class: 10,
-        };
+            speed: 30,
+            variant_encumbrance: false,
+            darkvision: 0,
+            languages: vec![],
+            resistances: vec![],
+            traits: vec![],
+        }
+    }
+
+    #[test]
+    fn test_apply_action() {
+        let mut state = State::new();
+
+        let attacker = test_character(1, "Attacker");
 
         let mut target = attacker.clone();
-        state.characters.push(attacker);
+        state.add_character(attacker).unwrap();
 
         target.id = 2;
         target.name = String::from("Target");
-        state.characters.push(target);
+        state.add_character(target).unwrap();
 
         let damage = Damage {
             dice: Dice {
@@ -776,12 +1822,684 @@ mod tests {
             actor_id: 1,
             target_id: 2,
             effect: Effect::Damage(damage),
+            range: AttackRange::Melee { reach: 5 },
+            weapon: None,
         });
 
         state.apply_action(action);
 
         // TODO: this test is brittle because it assumes that attack reached the target
-        let target = state.characters.iter().find(|c| c.id == 2).unwrap();
+        let target = state.characters.get(&2).unwrap();
         assert!(target.hit_points.current <= target.hit_points.max);
     }
+
+    #[test]
+    fn test_attack_against_total_cover() {
+        let mut state = State::new();
+        state.add_character(test_character(1, "Archer")).unwrap();
+        state.add_character(test_character(2, "Target")).unwrap();
+        state
+            .map
+            .place(1, Position::new(0, 2), Size::Medium)
+            .unwrap();
+        state
+            .map
+            .place(2, Position::new(6, 2), Size::Medium)
+            .unwrap();
+        for y in 0..5 {
+            state.map.set_terrain(Position::new(3, y), Terrain::Wall);
+        }
+
+        assert!(!state.can_see(1, 2));
+        assert_eq!(state.cover(1, 2), Cover::Total);
+
+        state.apply_action(Action::Attack(Attack {
+            name: String::from("Shortbow"),
+            actor_id: 1,
+            target_id: 2,
+            effect: Effect::Damage(Damage {
+                dice: Dice { count: 1, sides: 6 },
+                kind: DamageKind::Piercing,
+            }),
+            range: AttackRange::Ranged {
+                normal: 80,
+                long: 320,
+            },
+            weapon: None,
+        }));
+
+        let target = state.characters.get(&2).unwrap();
+        assert_eq!(target.hit_points.current, target.hit_points.max);
+    }
+
+    #[test]
+    fn test_leaving_reach_provokes_opportunity_attack() {
+        let mut state = State::new();
+        state.add_character(test_character(1, "Runner")).unwrap();
+        state.add_character(test_character(2, "Guard")).unwrap();
+        state
+            .map
+            .place(1, Position::new(0, 0), Size::Medium)
+            .unwrap();
+        state
+            .map
+            .place(2, Position::new(1, 0), Size::Medium)
+            .unwrap();
+        state.register_reaction(Reaction {
+            owner_id: 2,
+            kind: ReactionKind::OpportunityAttack(Attack {
+                name: String::from("Club"),
+                actor_id: 2,
+                target_id: 0,
+                effect: Effect::Damage(Damage {
+                    dice: Dice { count: 1, sides: 4 },
+                    kind: DamageKind::Bludgeoning,
+                }),
+                range: AttackRange::Melee { reach: 5 },
+                weapon: None,
+            }),
+        });
+        state.start_turn(1);
+
+        let result = state
+            .move_character(1, &[Position::new(0, 1), Position::new(0, 2)])
+            .unwrap();
+        assert_eq!(result.outcome, Outcome::Moved { feet: 10 });
+        assert_eq!(result.reactions.len(), 1);
+        assert_eq!(result.reactions[0].reactor_id, 2);
+        assert!(!state.has_reaction(2));
+
+        state.set_team(1, 1);
+        state.set_team(2, 1);
+        state.start_turn(2);
+        state.start_turn(1);
+        let result = state
+            .move_character(1, &[Position::new(1, 1), Position::new(2, 2)])
+            .unwrap();
+        assert!(result.reactions.is_empty());
+
+        // A readied action waiting on movement holds until a square is actually entered.
+        state.register_reaction(Reaction {
+            owner_id: 2,
+            kind: ReactionKind::Readied {
+                trigger: ReadyTrigger::CreatureMoves,
+                action: Box::new(Action::Dodge),
+            },
+        });
+        let result = state.move_character(1, &[]).unwrap();
+        assert_eq!(result.outcome, Outcome::Moved { feet: 0 });
+        assert!(result.reactions.is_empty());
+    }
+
+    #[test]
+    fn test_counterspell_interrupts_spell() {
+        let mut state = State::new();
+        state.add_character(test_character(1, "Wizard")).unwrap();
+        state.add_character(test_character(2, "Target")).unwrap();
+        state
+            .add_character(test_character(3, "Counterspeller"))
+            .unwrap();
+        state.register_reaction(Reaction {
+            owner_id: 3,
+            kind: ReactionKind::Counterspell,
+        });
+
+        let result = state.apply_action(Action::CastSpell(CastSpell {
+            name: String::from("Fire Bolt"),
+            actor_id: 1,
+            target_id: 2,
+            effect: Effect::Damage(Damage {
+                dice: Dice {
+                    count: 1,
+                    sides: 10,
+                },
+                kind: DamageKind::Fire,
+            }),
+            shape: Shape::NoShape,
+        }));
+
+        assert_eq!(result.outcome, Outcome::Countered);
+        assert_eq!(result.reactions[0].reactor_id, 3);
+        let target = state.characters.get(&2).unwrap();
+        assert_eq!(target.hit_points.current, target.hit_points.max);
+    }
+
+    #[test]
+    fn test_use_object_consumes_items() {
+        let mut state = State::new();
+        let mut cleric = test_character(1, "Cleric");
+        cleric.hit_points.current = cleric.hit_points.max - 3;
+        cleric.inventory.add(
+            Item::new("Potion of Healing", ItemCategory::Consumable)
+                .with_quantity(2)
+                .with_effects(vec![Effect::Healing(Healing {
+                    dice: Dice { count: 2, sides: 4 },
+                    bonus: 2,
+                })]),
+        );
+        cleric.inventory.add(
+            Item::new(
+                "Wand of Magic Missiles",
+                ItemCategory::Wondrous(WondrousItem {
+                    name: String::from("Wand of Magic Missiles"),
+                    slot: EquipmentSlot::MainHand,
+                    requires_attunement: false,
+                    effects: vec![],
+                }),
+            )
+            .with_charges(1)
+            .with_effects(vec![Effect::Damage(Damage {
+                dice: Dice { count: 3, sides: 4 },
+                kind: DamageKind::Force,
+            })]),
+        );
+        state.add_character(cleric).unwrap();
+        state.add_character(test_character(2, "Target")).unwrap();
+
+        let use_item = |item: &str, target_id: u32| {
+            Action::UseObject(UseObject {
+                actor_id: 1,
+                target_id,
+                item: String::from(item),
+            })
+        };
+
+        let result = state.apply_action(use_item("Potion of Healing", 1));
+        assert_eq!(result.outcome, Outcome::Healed { amount: 3 });
+        let cleric = state.characters.get(&1).unwrap();
+        assert_eq!(cleric.hit_points.current, cleric.hit_points.max);
+        assert_eq!(cleric.inventory.quantity_of("Potion of Healing"), 1);
+
+        // Nothing is used up on a target that isn't there.
+        let result = state.apply_action(use_item("Potion of Healing", 9));
+        assert_eq!(result.outcome, Outcome::Unavailable);
+        assert_eq!(
+            state.characters[&1]
+                .inventory
+                .quantity_of("Potion of Healing"),
+            1
+        );
+
+        let result = state.apply_action(use_item("Wand of Magic Missiles", 2));
+        assert!(matches!(result.outcome, Outcome::Hit { .. }));
+        let result = state.apply_action(use_item("Wand of Magic Missiles", 2));
+        assert_eq!(result.outcome, Outcome::Unavailable);
+
+        state.dawn();
+        let cleric = state.characters.get(&1).unwrap();
+        let wand = cleric.inventory.get("Wand of Magic Missiles").unwrap();
+        assert_eq!(wand.charges.as_ref().unwrap().current, 1);
+    }
+
+    #[test]
+    fn test_ammunition_is_spent_and_recovered() {
+        let mut state = State::new();
+        let mut archer = test_character(1, "Archer");
+        archer
+            .inventory
+            .add(Item::new("Arrow", ItemCategory::Gear).with_quantity(3));
+        state.add_character(archer).unwrap();
+        state.add_character(test_character(2, "Target")).unwrap();
+
+        let longbow = Weapon::new(
+            "Longbow",
+            WeaponCategory::Martial,
+            WeaponKind::Ranged,
+            Dice { count: 1, sides: 8 },
+            DamageKind::Piercing,
+        )
+        .with_properties(vec![WeaponProperty::Ammunition, WeaponProperty::TwoHanded])
+        .with_range(150, 600)
+        .with_ammunition("Arrow");
+        let shoot = Attack::with_weapon(1, 2, longbow, WeaponMode::TwoHanded).unwrap();
+
+        // A shot that can't reach the target never leaves the quiver.
+        state.map = BattleMap::new(200, 1);
+        for (id, x) in [(1, 0), (2, 150)] {
+            state
+                .map
+                .place(id, Position::new(x, 0), Size::Medium)
+                .unwrap();
+        }
+        let result = state.apply_action(Action::Attack(shoot.clone()));
+        assert_eq!(result.outcome, Outcome::OutOfRange);
+        assert_eq!(state.characters[&1].inventory.quantity_of("Arrow"), 3);
+        state.map.remove(2);
+
+        for _ in 0..3 {
+            let result = state.apply_action(Action::Attack(shoot.clone()));
+            assert_ne!(result.outcome, Outcome::Unavailable);
+        }
+        let result = state.apply_action(Action::Attack(shoot));
+        assert_eq!(result.outcome, Outcome::Unavailable);
+
+        state.recover_ammunition();
+        let archer = state.characters.get(&1).unwrap();
+        assert_eq!(archer.inventory.quantity_of("Arrow"), 1);
+    }
+
+    #[test]
+    fn test_extra_attack_and_sneak_attack() {
+        let mut state = State::new();
+        let mut fighter = test_character(1, "Fighter");
+        fighter.classes[0] = ClassDetails::new(&Class::Fighter);
+        fighter.classes[0].level = 5;
+        let mut rogue = test_character(2, "Rogue");
+        rogue.classes[0] = ClassDetails::new(&Class::Rogue);
+        rogue.classes[0].level = 3;
+        let mut target = test_character(3, "Target");
+        target.armor_class = -100;
+        target.hit_points.current = 1000;
+        for character in [fighter, rogue, target] {
+            state.add_character(character).unwrap();
+        }
+        state.set_team(1, 1);
+        state.set_team(2, 1);
+        for (id, x) in [(1, 0), (2, 2), (3, 1)] {
+            state
+                .map
+                .place(id, Position::new(x, 0), Size::Medium)
+                .unwrap();
+        }
+
+        // Deals exactly 1 damage, so anything more is Sneak Attack.
+        let needle = Weapon::new(
+            "Needle",
+            WeaponCategory::Simple,
+            WeaponKind::Melee,
+            Dice { count: 1, sides: 1 },
+            DamageKind::Piercing,
+        )
+        .with_properties(vec![WeaponProperty::Finesse]);
+
+        let slash = Attack::with_weapon(1, 3, needle.clone(), WeaponMode::OneHanded).unwrap();
+        assert_eq!(state.take_attack_action(slash).len(), 2);
+        let mut crossbow = needle.clone();
+        crossbow.properties.push(WeaponProperty::Loading);
+        let shot = Attack::with_weapon(1, 3, crossbow, WeaponMode::OneHanded).unwrap();
+        assert_eq!(state.take_attack_action(shot).len(), 1);
+
+        // The rogue's ally stands next to the target, so the first hit each turn sneak attacks.
+        let stab = Attack::with_weapon(2, 3, needle.clone(), WeaponMode::OneHanded).unwrap();
+        state.start_turn(2);
+        let results = state.take_attack_action(stab.clone());
+        assert_eq!(results.len(), 1);
+        assert!(matches!(results[0].outcome, Outcome::Hit { damage, .. } if damage >= 3));
+        let results = state.take_attack_action(stab.clone());
+        assert!(matches!(results[0].outcome, Outcome::Hit { damage: 1, .. }));
+        state.start_turn(2);
+        let results = state.take_attack_action(stab.clone());
+        assert!(matches!(results[0].outcome, Outcome::Hit { damage, .. } if damage >= 3));
+
+        // Uncanny Dodge halves the whole hit, Sneak Attack dice included, so it never
+        // deals more than half of 1 + 2d6.
+        state.register_reaction(Reaction {
+            owner_id: 3,
+            kind: ReactionKind::UncannyDodge,
+        });
+        for _ in 0..50 {
+            state.start_turn(2);
+            state.start_turn(3);
+            let results = state.take_attack_action(stab.clone());
+            assert_eq!(results[0].reactions[0].kind, ReactionKind::UncannyDodge);
+            assert!(matches!(results[0].outcome, Outcome::Hit { damage, .. } if damage <= 6));
+        }
+
+        // A halfling swinging a heavy weapon has disadvantage, which rules out Sneak Attack. The
+        // target's reaction is still spent, so the hit isn't halved either.
+        state.get_character_mut(2).unwrap().race = Race::Halfling;
+        let mut maul = needle;
+        maul.properties.push(WeaponProperty::Heavy);
+        let smash = Attack::with_weapon(2, 3, maul, WeaponMode::OneHanded).unwrap();
+        state.start_turn(2);
+        let results = state.take_attack_action(smash);
+        assert!(matches!(results[0].outcome, Outcome::Hit { damage: 1, .. }));
+    }
+
+    #[test]
+    fn test_defensive_duelist() {
+        let rapier = Weapon::new(
+            "Rapier",
+            WeaponCategory::Martial,
+            WeaponKind::Melee,
+            Dice { count: 1, sides: 8 },
+            DamageKind::Piercing,
+        )
+        .with_properties(vec![WeaponProperty::Finesse]);
+        let mut state = State::new();
+        let attacker = test_character(1, "Attacker");
+        let mut duelist = test_character(2, "Duelist");
+        duelist.armor_class = -100;
+        duelist.hit_points.current = 1000;
+        duelist.feats = vec![String::from("Defensive Duelist")];
+        duelist.weapon_proficiencies = vec![WeaponProficiency::Martial];
+        for character in [attacker, duelist] {
+            state.add_character(character).unwrap();
+        }
+        state
+            .map
+            .place(1, Position::new(0, 0), Size::Medium)
+            .unwrap();
+        state
+            .map
+            .place(2, Position::new(1, 0), Size::Medium)
+            .unwrap();
+
+        // Without a finesse weapon in hand the feat does nothing.
+        let attack = Attack::with_weapon(1, 2, rapier.clone(), WeaponMode::OneHanded).unwrap();
+        let result = state.resolve_attack(attack.clone());
+        assert!(result.reactions.is_empty());
+
+        state.get_character_mut(2).unwrap().equipment.main_hand = Some(rapier);
+        let result = state.resolve_attack(attack.clone());
+        assert_eq!(result.reactions[0].kind, ReactionKind::DefensiveDuelist);
+        assert_eq!(result.reactions[0].reactor_id, 2);
+
+        // Only one reaction a round.
+        let result = state.resolve_attack(attack);
+        assert!(result.reactions.is_empty());
+    }
+
+    #[test]
+    fn test_short_and_long_rests() {
+        let mut state = State::new();
+        let mut fighter = test_character(1, "Fighter");
+        fighter.classes[0].level = 3;
+        fighter.hit_points.max = 30;
+        fighter.hit_points.current = 10;
+        let mut warlock = test_character(2, "Warlock");
+        warlock.classes[0] = ClassDetails::new(&Class::Warlock);
+        warlock.classes[0].level = 2;
+        warlock.expend_spell_slot(1).unwrap();
+        warlock.expend_spell_slot(1).unwrap();
+        assert_eq!(
+            warlock.expend_spell_slot(1),
+            Err(SpellSlotError::NoSlotsLeft(1))
+        );
+        for character in [fighter, warlock, test_character(3, "Guard")] {
+            state.add_character(character).unwrap();
+        }
+
+        state.start_rest(RestKind::Short, &[1, 2]).unwrap();
+        assert_eq!(
+            state.start_rest(RestKind::Long, &[1, 2]),
+            Err(RestError::AlreadyResting)
+        );
+        assert_eq!(
+            state.finish_rest(&[(1, Class::Wizard)]),
+            Err(RestError::NoHitDice(Class::Wizard))
+        );
+        assert_eq!(
+            state.finish_rest(&[(3, Class::Fighter)]),
+            Err(RestError::NotInParty(3))
+        );
+        let healed = state
+            .finish_rest(&[(1, Class::Fighter), (1, Class::Fighter)])
+            .unwrap();
+        assert_eq!(healed.len(), 1);
+        assert!(healed[0].1 >= 2);
+        let fighter = &state.characters[&1];
+        assert_eq!(fighter.hit_points.current, 10 + healed[0].1);
+        assert_eq!(fighter.remaining_hit_dice()[0].count, 1);
+        assert_eq!(state.characters[&2].remaining_spell_slots()[0], 2);
+
+        // Taking damage interrupts the rest and nobody benefits.
+        state.start_rest(RestKind::Long, &[1, 2]).unwrap();
+        state.deal_damage(1, 1, &DamageKind::Fire);
+        assert_eq!(state.finish_rest(&[]), Err(RestError::Interrupted));
+        assert!(state.characters[&1].hit_points.current < 30);
+
+        // Half of the three hit dice, rounded down, come back.
+        state.start_rest(RestKind::Long, &[1, 2]).unwrap();
+        assert_eq!(
+            state.finish_rest(&[(1, Class::Fighter)]),
+            Err(RestError::NotShortRest)
+        );
+        state.finish_rest(&[]).unwrap();
+        assert_eq!(state.characters[&1].hit_points.current, 30);
+        assert_eq!(state.characters[&1].remaining_hit_dice()[0].count, 2);
+    }
+
+    #[test]
+    fn test_class_resources() {
+        let mut state = State::new();
+        let classes = [
+            Class::Barbarian,
+            Class::Monk,
+            Class::Sorcerer,
+            Class::Bard,
+            Class::Cleric,
+        ];
+        for (id, class) in (1..).zip(classes) {
+            let mut character = test_character(id, &format!("{:?}", class));
+            character.classes[0] = ClassDetails::new(&class);
+            character.classes[0].level = 3;
+            character.hit_points.max = 1000;
+            character.hit_points.current = 1000;
+            state.add_character(character).unwrap();
+        }
+        let use_feature = |actor_id, feature| Action::UseFeature(UseFeature { actor_id, feature });
+
+        // Three rages at 3rd level, each granting resistance and +2 damage.
+        for _ in 0..3 {
+            let result = state.apply_action(use_feature(1, FeatureAction::Rage));
+            assert_eq!(result.outcome, Outcome::Applied);
+        }
+        let result = state.apply_action(use_feature(1, FeatureAction::Rage));
+        assert_eq!(result.outcome, Outcome::Unavailable);
+        let barbarian = &state.characters[&1];
+        assert!(barbarian.is_resistant_to(&DamageKind::Slashing));
+        assert_eq!(barbarian.modifier(EnhancementBuff::DamageRoll), 6);
+
+        let unarmed_strike = Attack {
+            name: String::from("Unarmed Strike"),
+            actor_id: 2,
+            target_id: 1,
+            effect: Effect::Damage(Damage {
+                dice: Dice { count: 1, sides: 4 },
+                kind: DamageKind::Bludgeoning,
+            }),
+            range: AttackRange::Melee { reach: 5 },
+            weapon: None,
+        };
+        assert_eq!(state.flurry_of_blows(unarmed_strike.clone()).len(), 2);
+        assert_eq!(state.characters[&2].resource_remaining("Ki"), Some(2));
+        assert_eq!(
+            state.flurry_of_blows(Attack {
+                actor_id: 1,
+                ..unarmed_strike
+            })[0]
+                .outcome,
+            Outcome::Unavailable
+        );
+
+        // Font of Magic turns a 2nd level slot into points and points into a 1st level slot.
+        let sorcerer = state.get_character_mut(3).unwrap();
+        assert_eq!(
+            sorcerer.create_spell_slot(1),
+            Err(ResourceError::NoExpendedSlot(1))
+        );
+        sorcerer.expend_spell_slot(1).unwrap();
+        sorcerer.create_spell_slot(1).unwrap();
+        assert_eq!(sorcerer.resource_remaining("Sorcery Points"), Some(1));
+        let result = state.apply_action(use_feature(3, FeatureAction::ConvertSpellSlot(2)));
+        assert_eq!(result.outcome, Outcome::Applied);
+        let sorcerer = &state.characters[&3];
+        assert_eq!(sorcerer.resource_remaining("Sorcery Points"), Some(3));
+        assert_eq!(sorcerer.remaining_spell_slots()[..2], [4, 1]);
+
+        let inspire = use_feature(4, FeatureAction::BardicInspiration { target_id: 1 });
+        assert_eq!(
+            state.apply_action(inspire.clone()).outcome,
+            Outcome::Applied
+        );
+        assert_eq!(state.apply_action(inspire).outcome, Outcome::Unavailable);
+        assert!(state
+            .get_character_mut(1)
+            .unwrap()
+            .use_inspiration()
+            .is_some_and(|roll| roll <= 6));
+
+        let preserve_life = FeatureAction::ChannelDivinity {
+            target_id: 2,
+            effect: Effect::Healing(Healing {
+                dice: Dice { count: 0, sides: 0 },
+                bonus: 15,
+            }),
+        };
+        state.get_character_mut(2).unwrap().hit_points.current = 1;
+        let result = state.apply_action(use_feature(5, preserve_life.clone()));
+        assert_eq!(result.outcome, Outcome::Healed { amount: 15 });
+        assert_eq!(
+            state.apply_action(use_feature(5, preserve_life)).outcome,
+            Outcome::Unavailable
+        );
+
+        // Ki and Channel Divinity come back on a short rest, rages only on a long rest.
+        state.start_rest(RestKind::Short, &[1, 2, 3, 4, 5]).unwrap();
+        state.finish_rest(&[]).unwrap();
+        assert_eq!(state.characters[&2].resource_remaining("Ki"), Some(3));
+        assert_eq!(
+            state.characters[&5].resource_remaining("Channel Divinity"),
+            Some(1)
+        );
+        assert_eq!(state.characters[&1].resource_remaining("Rage"), Some(0));
+        state.start_rest(RestKind::Long, &[1, 2, 3, 4, 5]).unwrap();
+        state.finish_rest(&[]).unwrap();
+        assert_eq!(state.characters[&1].resource_remaining("Rage"), Some(3));
+
+        // Primal Champion: rages no longer run out.
+        let barbarian = state.get_character_mut(1).unwrap();
+        barbarian.classes[0].level = 20;
+        for _ in 0..10 {
+            assert_eq!(barbarian.spend_resource("Rage", 1), Ok(UNLIMITED));
+        }
+    }
+
+    #[test]
+    fn test_save_and_load_session() {
+        let mut state = State::new();
+        let mut wizard = test_character(1, "Wizard");
+        wizard.hit_points.current = 4;
+        for character in [wizard, test_character(2, "Goblin")] {
+            state.add_character(character).unwrap();
+        }
+        state.set_team(1, 1);
+        state.set_team(2, 2);
+        state
+            .map
+            .place(1, Position::new(0, 0), Size::Medium)
+            .unwrap();
+        state.map.set_terrain(Position::new(1, 1), Terrain::Wall);
+        state.register_reaction(Reaction {
+            owner_id: 1,
+            kind: ReactionKind::Shield,
+        });
+        state.start_rest(RestKind::Short, &[1]).unwrap();
+
+        let path = std::env::temp_dir().join("vvt_test_session.json");
+        save_session(&state, &path).unwrap();
+        let loaded = load_session(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.characters, state.characters);
+        assert_eq!(loaded.map, state.map);
+        assert_eq!(loaded.rest, state.rest);
+        assert!(loaded.are_hostile(1, 2));
+        assert!(loaded.has_reaction(1));
+
+        // Version 1 sessions listed characters, often all with id 1.
+        let mut document = serde_json::to_value(&state).unwrap();
+        document["characters"] = serde_json::json!([
+            state.characters[&1],
+            state.characters[&2],
+            test_character(1, "Hireling")
+        ]);
+        let old = serde_json::json!({ "version": 1, "data": document });
+        std::fs::write(&path, old.to_string()).unwrap();
+        let mut loaded = load_session(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.get_character(3).unwrap().name, "Hireling");
+        assert_eq!(loaded.allocate_id(), 4);
+    }
+
+    #[test]
+    fn test_character_ids() {
+        let mut state = State::new();
+        let id = state.allocate_id();
+        state.add_character(test_character(id, "Wizard")).unwrap();
+        assert_eq!(
+            state.add_character(test_character(id, "Impostor")),
+            Err(StateError::DuplicateId(id))
+        );
+        state.add_character(test_character(5, "Goblin")).unwrap();
+        assert_eq!(state.allocate_id(), 6);
+
+        // Spells are cast by their actor rather than whoever has the first id.
+        state.get_character_mut(5).unwrap().abilities.intelligence = Ability { value: 50 };
+        state.get_character_mut(1).unwrap().armor_class = 21;
+        let fire_bolt = Action::CastSpell(CastSpell {
+            name: String::from("Fire Bolt"),
+            actor_id: 5,
+            target_id: 1,
+            effect: Effect::Damage(Damage {
+                dice: Dice {
+                    count: 1,
+                    sides: 10,
+                },
+                kind: DamageKind::Fire,
+            }),
+            shape: Shape::NoShape,
+        });
+        assert!(matches!(
+            state.apply_action(fire_bolt.clone()).outcome,
+            Outcome::Hit { .. }
+        ));
+        // AC bonuses such as Shield's count against spell attacks too.
+        state.armor_class_bonus.insert(1, 25);
+        assert_eq!(state.apply_action(fire_bolt.clone()).outcome, Outcome::Miss);
+        state.start_turn(1);
+
+        state.set_team(5, 1);
+        state
+            .map
+            .place(5, Position::new(0, 0), Size::Medium)
+            .unwrap();
+        state.start_rest(RestKind::Short, &[1, 5]).unwrap();
+        assert_eq!(state.remove_character(5).unwrap().name, "Goblin");
+        assert_eq!(
+            state.finish_rest(&[(5, Class::Fighter)]),
+            Err(RestError::NotInParty(5))
+        );
+        assert!(state.map.position_of(5).is_none());
+        assert!(state.get_character(5).is_none());
+        assert_eq!(state.apply_action(fire_bolt).outcome, Outcome::Unavailable);
+    }
+
+    #[test]
+    fn test_encumbered_saving_throw() {
+        let mut state = State::new();
+        let mut mule = test_character(1, "Mule");
+        mule.variant_encumbrance = true;
+        mule.inventory.add(
+            Item::new("Iron Ingot", ItemCategory::Gear)
+                .with_weight(10.0)
+                .with_quantity(12),
+        );
+        state.add_character(mule).unwrap();
+
+        // Heavily encumbered, so Dexterity saves take the lower of two d20s.
+        seed_rng(7);
+        let normal = Dice::roll_d20(RollMode::Normal).total;
+        seed_rng(7);
+        let disadvantage = Dice::roll_d20(RollMode::Disadvantage).total;
+        assert_ne!(normal, disadvantage);
+        seed_rng(7);
+        assert_eq!(
+            state.saving_throw(1, SavingThrow::Dexterity, 1),
+            Some(disadvantage)
+        );
+        seed_rng(7);
+        assert_eq!(state.saving_throw(1, SavingThrow::Wisdom, 1), Some(normal));
+    }
 }